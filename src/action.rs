@@ -0,0 +1,123 @@
+use crate::grammar::Tag;
+use crate::lexer::literal_len;
+use crate::lexer::tag_len;
+use crate::token::Span;
+use crate::token::Spanned;
+
+// A reference to a semantic value or location inside an action, e.g. `$1`
+// or `@$`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub kind: ReferenceKind,
+    pub target: Target,
+    // $<tag>1, which overrides the declared type of the value
+    pub tag: Option<Tag>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    // $$, $1 or $name
+    Value,
+    // @$, @1 or @name
+    Location,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    // $$, the left-hand side
+    Lhs,
+    // $1, a position on the right-hand side counting from 1. Zero and
+    // negative positions, e.g. `$0` or `$-1`, refer to values on the stack
+    // before the rule.
+    Index(i32),
+    // $name or $[name], a symbol referred to by its name
+    Name(String),
+}
+
+/// Finds the value and location references in `code`, skipping C string
+/// literals, character literals and comments.
+pub fn references(code: &Spanned<String>) -> Vec<Reference> {
+    let text = code.data.as_str();
+    let mut references = Vec::new();
+    let mut pos = 0;
+    while let Some(c) = text[pos..].chars().next() {
+        let start = pos;
+        pos += c.len_utf8();
+        match c {
+            '$' | '@' => {
+                if let Some((reference, end)) = reference(text, start) {
+                    references.push(Reference {
+                        span: code.span.start + start..code.span.start + end,
+                        ..reference
+                    });
+                    pos = end;
+                }
+            }
+            '"' | '\'' => pos += literal_len(&text[pos..], c),
+            '/' if text[pos..].starts_with('/') => {
+                pos = text[pos..].find('\n').map_or(text.len(), |i| pos + i);
+            }
+            '/' if text[pos..].starts_with('*') => {
+                pos = text[pos + 1..]
+                    .find("*/")
+                    .map_or(text.len(), |i| pos + 1 + i + 2);
+            }
+            _ => {}
+        }
+    }
+    references
+}
+
+// Parses the reference starting with the `$` or `@` at `start`, returning it
+// together with the offset of its end. The span is relative to `text`.
+fn reference(text: &str, start: usize) -> Option<(Reference, usize)> {
+    let kind = match &text[start..start + 1] {
+        "$" => ReferenceKind::Value,
+        _ => ReferenceKind::Location,
+    };
+    let mut pos = start + 1;
+
+    // $<tag>1
+    let mut tag = None;
+    if kind == ReferenceKind::Value && text[pos..].starts_with('<') {
+        // Tags end like in the lexer, so an unterminated `$<` ends at the
+        // end of the statement
+        let end = pos + tag_len(&text[pos + 1..]).ok()?;
+        let name = text[pos + 1..end].trim();
+        tag = Some(match name {
+            "" => Tag::Untyped,
+            "*" => Tag::Any,
+            _ => Tag::Named(name.to_string()),
+        });
+        pos = end + 1;
+    }
+
+    let rest = &text[pos..];
+    let (target, len) = if rest.starts_with('$') {
+        (Target::Lhs, 1)
+    } else if rest.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+        let digits = rest[1..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(rest.len(), |i| i + 1);
+        (Target::Index(rest[..digits].parse().ok()?), digits)
+    } else if let Some(bracketed) = rest.strip_prefix('[') {
+        let end = bracketed.find(']')?;
+        (Target::Name(bracketed[..end].trim().to_string()), end + 2)
+    } else if rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        let end = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(rest.len());
+        (Target::Name(rest[..end].to_string()), end)
+    } else {
+        return None;
+    };
+    let end = pos + len;
+    let reference = Reference {
+        kind,
+        target,
+        tag,
+        span: start..end,
+    };
+    Some((reference, end))
+}
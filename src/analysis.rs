@@ -0,0 +1,145 @@
+use std::collections::BTreeSet;
+
+use crate::grammar::Grammar;
+use crate::symbols::SymbolId;
+use crate::symbols::SymbolKind;
+use crate::symbols::SymbolTable;
+
+// The nullable symbols and the FIRST and FOLLOW sets of a grammar. Mid-rule
+// actions derive only the empty string, so they are skipped. Symbols which
+// are not nonterminals, including undefined ones, are treated as terminals.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    symbols: SymbolTable,
+    // The rules as `lhs: rhs`
+    productions: Vec<(SymbolId, Vec<SymbolId>)>,
+    nullable: Vec<bool>,
+    first: Vec<BTreeSet<SymbolId>>,
+    follow: Vec<BTreeSet<SymbolId>>,
+}
+
+impl Analysis {
+    pub fn new(grammar: &Grammar) -> Self {
+        let symbols = SymbolTable::new(grammar);
+        let id = |name: &str| symbols.id(name).expect("every symbol is interned");
+        let productions = grammar
+            .rules
+            .iter()
+            .flat_map(|rule| {
+                rule.alternatives.iter().map(|alternative| {
+                    let rhs = alternative.symbols().map(|symbol| id(&symbol.data));
+                    (id(&rule.name.data), rhs.collect())
+                })
+            })
+            .collect();
+        let mut analysis = Analysis {
+            nullable: vec![false; symbols.len()],
+            first: vec![BTreeSet::new(); symbols.len()],
+            follow: vec![BTreeSet::new(); symbols.len()],
+            symbols,
+            productions,
+        };
+        analysis.compute_nullable();
+        analysis.compute_first();
+        if let Ok(start) = grammar.start_symbol() {
+            let start = analysis.symbols.id(&start.data).unwrap();
+            analysis.follow[start.index()].insert(SymbolId::END);
+        }
+        analysis.compute_follow();
+        analysis
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Whether `symbol` derives the empty string.
+    pub fn is_nullable(&self, symbol: SymbolId) -> bool {
+        self.nullable[symbol.index()]
+    }
+
+    /// The terminals which can begin a string derived from `symbol`.
+    pub fn first(&self, symbol: SymbolId) -> &BTreeSet<SymbolId> {
+        &self.first[symbol.index()]
+    }
+
+    /// The terminals which can appear directly after `symbol` in a sentential
+    /// form, including `$end` if it can end the input.
+    pub fn follow(&self, symbol: SymbolId) -> &BTreeSet<SymbolId> {
+        &self.follow[symbol.index()]
+    }
+
+    /// Whether every symbol of `string` is nullable.
+    pub fn is_nullable_string(&self, string: &[SymbolId]) -> bool {
+        string.iter().all(|&symbol| self.is_nullable(symbol))
+    }
+
+    /// The terminals which can begin a string derived from `string`.
+    pub fn first_of_string(&self, string: &[SymbolId]) -> BTreeSet<SymbolId> {
+        let mut first = BTreeSet::new();
+        for &symbol in string {
+            first.extend(self.first(symbol));
+            if !self.is_nullable(symbol) {
+                break;
+            }
+        }
+        first
+    }
+
+    fn is_terminal(&self, symbol: SymbolId) -> bool {
+        self.symbols.get(symbol).kind != SymbolKind::Nonterminal
+    }
+
+    fn compute_nullable(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (lhs, rhs) in &self.productions {
+                if !self.nullable[lhs.index()] && self.is_nullable_string(rhs) {
+                    self.nullable[lhs.index()] = true;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    fn compute_first(&mut self) {
+        for (id, _) in self.symbols.iter() {
+            if self.is_terminal(id) {
+                self.first[id.index()].insert(id);
+            }
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (lhs, rhs) in &self.productions {
+                let first = self.first_of_string(rhs);
+                let len = self.first[lhs.index()].len();
+                self.first[lhs.index()].extend(first);
+                changed |= self.first[lhs.index()].len() != len;
+            }
+        }
+    }
+
+    fn compute_follow(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (lhs, rhs) in &self.productions {
+                for (i, &symbol) in rhs.iter().enumerate() {
+                    if self.is_terminal(symbol) {
+                        continue;
+                    }
+                    let rest = &rhs[i + 1..];
+                    let mut follow = self.first_of_string(rest);
+                    if self.is_nullable_string(rest) {
+                        follow.extend(&self.follow[lhs.index()]);
+                    }
+                    let len = self.follow[symbol.index()].len();
+                    self.follow[symbol.index()].extend(follow);
+                    changed |= self.follow[symbol.index()].len() != len;
+                }
+            }
+        }
+    }
+}
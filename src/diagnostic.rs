@@ -0,0 +1,191 @@
+use crate::error::line_col;
+use crate::token::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+// A message attached to a span of the input, rendered in the style of rustc:
+//
+// error: unexpected '=', expected '|' or ';'
+//  --> calc.y:6:12
+//   |
+// 6 | expr: expr = term
+//   |            ^ unexpected '='
+//   |
+//   = help: ...
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    // Printed next to the underline.
+    pub label: Option<String>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+// ANSI escape codes, or empty strings when colour is disabled.
+struct Style {
+    severity: &'static str,
+    gutter: &'static str,
+    bold: &'static str,
+    reset: &'static str,
+}
+
+impl Style {
+    fn new(severity: Severity, color: bool) -> Self {
+        if !color {
+            return Style {
+                severity: "",
+                gutter: "",
+                bold: "",
+                reset: "",
+            };
+        }
+        Style {
+            severity: match severity {
+                Severity::Error => "\x1b[1;31m",
+                Severity::Warning => "\x1b[1;33m",
+            },
+            gutter: "\x1b[1;34m",
+            bold: "\x1b[1m",
+            reset: "\x1b[0m",
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>, span: Span) -> Self {
+        Diagnostic {
+            severity,
+            message: message.into(),
+            span,
+            label: None,
+            notes: Vec::new(),
+            help: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>, span: Span) -> Self {
+        Diagnostic::new(Severity::Error, message, span)
+    }
+
+    pub fn warning(message: impl Into<String>, span: Span) -> Self {
+        Diagnostic::new(Severity::Warning, message, span)
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    /// Renders the diagnostic with the lines of `input` covered by its span,
+    /// underlining the span. `path` is only used for the location header.
+    pub fn render(&self, input: &str, path: &str, color: bool) -> String {
+        let style = Style::new(self.severity, color);
+        let start = floor_char_boundary(input, self.span.start);
+        let end = floor_char_boundary(input, self.span.end.max(start));
+
+        // Byte ranges of the lines covered by the span, excluding newlines.
+        let mut lines = Vec::new();
+        let mut line_start = 0;
+        for line in input.split('\n') {
+            let line_end = line_start + line.len();
+            let last = if end > start { end - 1 } else { start };
+            if line_end >= start && line_start <= last {
+                lines.push(line_start..line_end);
+            }
+            if line_start > last {
+                break;
+            }
+            line_start = line_end + 1;
+        }
+
+        let (line, col) = line_col(input, start);
+        let last_line = line + lines.len().saturating_sub(1);
+        let width = last_line.to_string().len();
+        let pad = " ".repeat(width);
+
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let Style {
+            severity: sev,
+            gutter,
+            bold,
+            reset,
+        } = style;
+
+        let mut out = String::new();
+        out.push_str(&format!(
+            "{sev}{severity}{reset}{bold}: {}{reset}\n",
+            self.message
+        ));
+        out.push_str(&format!("{pad}{gutter}-->{reset} {path}:{line}:{col}\n"));
+        out.push_str(&format!("{pad} {gutter}|{reset}\n"));
+        for (i, range) in lines.iter().enumerate() {
+            let text = input[range.clone()].trim_end_matches('\r');
+            let from = start.max(range.start) - range.start;
+            let to = (end.min(range.end) - range.start).max(from);
+            let from = floor_char_boundary(text, from);
+            let to = floor_char_boundary(text, to);
+            let indent = display_width(&text[..from]);
+            let underline = display_width(&text[from..to]).max(1);
+            out.push_str(&format!("{gutter}{:>width$} |{reset}", line + i));
+            if !text.is_empty() {
+                out.push_str(&format!(" {}", text.replace('\t', "    ")));
+            }
+            out.push('\n');
+            out.push_str(&format!(
+                "{pad} {gutter}|{reset} {}{sev}{}",
+                " ".repeat(indent),
+                "^".repeat(underline)
+            ));
+            if let Some(label) = self.label.as_ref().filter(|_| i + 1 == lines.len()) {
+                out.push_str(&format!(" {label}"));
+            }
+            out.push_str(&format!("{reset}\n"));
+        }
+        if !self.notes.is_empty() || !self.help.is_empty() {
+            out.push_str(&format!("{pad} {gutter}|{reset}\n"));
+        }
+        for note in &self.notes {
+            out.push_str(&format!(
+                "{pad} {gutter}={reset} {bold}note{reset}: {note}\n"
+            ));
+        }
+        for help in &self.help {
+            out.push_str(&format!(
+                "{pad} {gutter}={reset} {bold}help{reset}: {help}\n"
+            ));
+        }
+        out
+    }
+}
+
+// Width of `s` in columns, expanding tabs to four spaces.
+fn display_width(s: &str) -> usize {
+    s.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+fn floor_char_boundary(s: &str, mut pos: usize) -> usize {
+    pos = pos.min(s.len());
+    while !s.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    pos
+}
@@ -1,33 +1,88 @@
+use std::collections::BTreeSet;
+
+use crate::action::Reference;
+use crate::action::ReferenceKind;
+use crate::action::Target;
+use crate::analysis::Analysis;
+use crate::error::GrammarError;
+use crate::error::ParseError;
 use crate::grammar::Alternative;
+use crate::grammar::DefineValue;
 use crate::grammar::Directive;
 use crate::grammar::Grammar;
+use crate::grammar::Item;
 use crate::grammar::Rule;
+use crate::grammar::SymbolOrTag;
+use crate::grammar::Tag;
+use crate::lint::Lint;
+use crate::symbols::SymbolId;
+use crate::token::Spanned;
+use crate::token::Token;
+use crate::token::TokenKind;
 
 impl std::fmt::Display for Grammar {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for directive in &self.directives {
             writeln!(f, "{}", directive)?;
         }
+        writeln!(f, "%%")?;
         for rule in &self.rules {
             writeln!(f, "{}", rule)?;
         }
-        for program in self.programs.lines() {
-            writeln!(f, "{}", program)?;
+        if let Some(programs) = &self.programs {
+            write!(f, "%%{}", programs)?;
         }
         Ok(())
     }
 }
 
+impl<T: std::fmt::Display> std::fmt::Display for Spanned<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.data.fmt(f)
+    }
+}
+
 impl std::fmt::Display for Directive {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Directive::PureParser => writeln!(f, "%pure-parser"),
-            Directive::Expect { number } => writeln!(f, "%expect {}", number),
-            Directive::NamePrefix { prefix } => writeln!(f, "%name-prefix=\"{}\"", prefix),
-            Directive::Locations => writeln!(f, "%locations"),
-            Directive::ParseParam { params } => writeln!(f, "%parse-param {{ {} }}", params),
-            Directive::LexProgram { params } => writeln!(f, "%lex-param {{ {} }}", params),
-            Directive::Union { code } => writeln!(f, "%union {{ {} }}", code),
+            Directive::Prologue { code } => write!(f, "%{{{}%}}", code),
+            Directive::Start { symbol } => write!(f, "%start {}", symbol),
+            Directive::Define { name, value } => {
+                write!(f, "%define {}", name)?;
+                if let Some(value) = value {
+                    write!(f, " {}", value)?;
+                }
+                Ok(())
+            }
+            Directive::Code { qualifier, code } => {
+                write!(f, "%code")?;
+                if let Some(qualifier) = qualifier {
+                    write!(f, " {}", qualifier)?;
+                }
+                write!(f, " {}", code)
+            }
+            Directive::Destructor { code, symbols } => {
+                write!(f, "%destructor {}", code)?;
+                for symbol in symbols {
+                    write!(f, " {}", symbol)?;
+                }
+                Ok(())
+            }
+            Directive::Printer { code, symbols } => {
+                write!(f, "%printer {}", code)?;
+                for symbol in symbols {
+                    write!(f, " {}", symbol)?;
+                }
+                Ok(())
+            }
+            Directive::InitialAction { code } => write!(f, "%initial-action {}", code),
+            Directive::PureParser => write!(f, "%pure-parser"),
+            Directive::Expect { number } => write!(f, "%expect {}", number),
+            Directive::NamePrefix { prefix } => write!(f, "%name-prefix={}", prefix),
+            Directive::Locations => write!(f, "%locations"),
+            Directive::ParseParam { params } => write!(f, "%parse-param {}", params),
+            Directive::LexProgram { params } => write!(f, "%lex-param {}", params),
+            Directive::Union { code } => write!(f, "%union {}", code),
             Directive::Type {
                 type_name,
                 rule_names,
@@ -36,51 +91,119 @@ impl std::fmt::Display for Directive {
                 for rule_name in rule_names {
                     write!(f, " {}", rule_name)?;
                 }
-                writeln!(f)
+                Ok(())
             }
-            Directive::Token {
-                token_name,
-                rule_names,
-            } => {
+            Directive::Token { tokens } => {
                 write!(f, "%token")?;
-                if let Some(token) = token_name {
-                    write!(f, " {}", token)?;
-                }
-                for rule_name in rule_names {
-                    write!(f, " {}", rule_name)?;
+                let mut tag = None;
+                for token in tokens {
+                    let token_tag = token.tag.as_ref().map(|tag| &tag.data);
+                    if token_tag != tag {
+                        if let Some(token_tag) = token_tag {
+                            write!(f, " {}", token_tag)?;
+                        }
+                        tag = token_tag;
+                    }
+                    write!(f, " {}", token.name)?;
+                    if let Some(code) = &token.code {
+                        write!(f, " {}", code)?;
+                    }
+                    if let Some(alias) = &token.alias {
+                        write!(f, " {}", alias)?;
+                    }
                 }
-                writeln!(f)
+                Ok(())
             }
             Directive::Left { rule_names } => {
                 write!(f, "%left")?;
                 for rule_name in rule_names {
                     write!(f, " {}", rule_name)?;
                 }
-                writeln!(f)
+                Ok(())
             }
             Directive::Right { rule_names } => {
                 write!(f, "%right")?;
                 for rule_name in rule_names {
                     write!(f, " {}", rule_name)?;
                 }
-                writeln!(f)
+                Ok(())
             }
             Directive::NonAssoc { rule_names } => {
                 write!(f, "%nonassoc")?;
                 for rule_name in rule_names {
                     write!(f, " {}", rule_name)?;
                 }
-                writeln!(f)
+                Ok(())
+            }
+            Directive::Precedence { rule_names } => {
+                write!(f, "%precedence")?;
+                for rule_name in rule_names {
+                    write!(f, " {}", rule_name)?;
+                }
+                Ok(())
+            }
+            Directive::Nterm {
+                type_name,
+                rule_names,
+            } => {
+                write!(f, "%nterm")?;
+                if let Some(type_name) = type_name {
+                    write!(f, " {}", type_name)?;
+                }
+                for rule_name in rule_names {
+                    write!(f, " {}", rule_name)?;
+                }
+                Ok(())
             }
         }
     }
 }
 
+impl std::fmt::Display for DefineValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DefineValue::Keyword(keyword) => write!(f, "{}", keyword),
+            DefineValue::String(string) => write!(f, "\"{}\"", string),
+            DefineValue::Code(code) => write!(f, "{{{}}}", code),
+        }
+    }
+}
+
+impl std::fmt::Display for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tag::Named(name) => write!(f, "<{}>", name),
+            Tag::Any => write!(f, "<*>"),
+            Tag::Untyped => write!(f, "<>"),
+        }
+    }
+}
+
+impl std::fmt::Display for SymbolOrTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SymbolOrTag::Symbol(name) => write!(f, "{}", name),
+            SymbolOrTag::Tag(tag) => write!(f, "{}", tag),
+        }
+    }
+}
+
 impl std::fmt::Display for Rule {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "{}:", self.name)?;
-        for alternative in &self.alternatives {
-            writeln!(f, "    | {}", alternative)?;
+        write!(f, "{}", self.name)?;
+        if let Some(named_ref) = &self.named_ref {
+            write!(f, "[{}]", named_ref)?;
+        }
+        writeln!(f, ":")?;
+        for (i, alternative) in self.alternatives.iter().enumerate() {
+            let alternative = alternative.to_string();
+            match (i, alternative.is_empty()) {
+                // An empty first alternative needs no line of its own
+                (0, true) => {}
+                (0, false) => writeln!(f, "    {}", alternative)?,
+                (_, true) => writeln!(f, "    |")?,
+                (_, false) => writeln!(f, "    | {}", alternative)?,
+            }
         }
         writeln!(f, ";")
     }
@@ -88,14 +211,183 @@ impl std::fmt::Display for Rule {
 
 impl std::fmt::Display for Alternative {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for (i, element) in self.elements.iter().enumerate() {
-            if i > 0 {
-                write!(f, " ")?;
+        let mut separator = "";
+        if self.empty.is_some() {
+            write!(f, "%empty")?;
+            separator = " ";
+        }
+        for item in &self.items {
+            match item {
+                Item::Symbol { symbol, named_ref } => {
+                    write!(f, "{}{}", separator, symbol)?;
+                    if let Some(named_ref) = named_ref {
+                        write!(f, "[{}]", named_ref)?;
+                    }
+                }
+                Item::Action(action) => write!(f, "{}{}", separator, action)?,
             }
-            write!(f, "{}", element)?;
+            separator = " ";
+        }
+        if let Some(precedence) = &self.precedence {
+            write!(f, "{}%prec {}", separator, precedence)?;
+            separator = " ";
         }
         if let Some(action) = &self.action {
-            write!(f, " {}", action)?;
+            write!(f, "{}{}", separator, action)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Reference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ReferenceKind::Value => write!(f, "$")?,
+            ReferenceKind::Location => write!(f, "@")?,
+        }
+        if let Some(tag) = &self.tag {
+            write!(f, "{}", tag)?;
+        }
+        match &self.target {
+            Target::Lhs => write!(f, "$"),
+            Target::Index(index) => write!(f, "{}", index),
+            Target::Name(name) if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => {
+                write!(f, "{}", name)
+            }
+            Target::Name(name) => write!(f, "[{}]", name),
+        }
+    }
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind())
+    }
+}
+
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenKind::Equal => write!(f, "'='"),
+            TokenKind::PercentPercent => write!(f, "'%%'"),
+            TokenKind::GreaterThan => write!(f, "'>'"),
+            TokenKind::LessThan => write!(f, "'<'"),
+            TokenKind::Bar => write!(f, "'|'"),
+            TokenKind::Colon => write!(f, "':'"),
+            TokenKind::SemiColon => write!(f, "';'"),
+            TokenKind::Code => write!(f, "code block"),
+            TokenKind::Prologue => write!(f, "prologue block"),
+            TokenKind::Epilogue => write!(f, "epilogue"),
+            TokenKind::Ident => write!(f, "identifier"),
+            TokenKind::String => write!(f, "string literal"),
+            TokenKind::Eof => write!(f, "end of file"),
+            TokenKind::Directive => write!(f, "directive"),
+            TokenKind::Char => write!(f, "character literal"),
+            TokenKind::Number => write!(f, "number"),
+            TokenKind::Type => write!(f, "type tag"),
+            TokenKind::NamedRef => write!(f, "named reference"),
+            TokenKind::UnterminatedCode => write!(f, "unterminated code block"),
+            TokenKind::Err => write!(f, "invalid token"),
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnexpectedToken { found, expected } => {
+                write!(f, "unexpected {}", found.data)?;
+                write_expected(f, expected)
+            }
+            ParseError::UnknownDirective { name } => {
+                write!(f, "unknown directive `{}`", name.data)
+            }
+            ParseError::UnterminatedCodeBlock { .. } => write!(f, "unterminated code block"),
+            ParseError::BadNumber { number } => write!(f, "invalid number `{}`", number.data),
+            ParseError::UnexpectedEof { expected, .. } => {
+                write!(f, "unexpected end of file")?;
+                write_expected(f, expected)
+            }
+        }
+    }
+}
+
+// Writes ", expected a, b or c".
+fn write_expected(f: &mut std::fmt::Formatter<'_>, expected: &[TokenKind]) -> std::fmt::Result {
+    for (i, token) in expected.iter().enumerate() {
+        if i == 0 {
+            write!(f, ", expected ")?;
+        } else if i == expected.len() - 1 {
+            write!(f, " or ")?;
+        } else {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", token)?;
+    }
+    Ok(())
+}
+
+impl std::fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GrammarError::UndefinedStartSymbol { symbol } => {
+                write!(f, "start symbol `{}` has no rules", symbol)
+            }
+            GrammarError::NoRules { .. } => write!(f, "no rules in the input grammar"),
+            GrammarError::UnknownSymbol { symbol } => {
+                write!(f, "symbol `{}` is not defined", symbol)
+            }
+            GrammarError::UnknownTag { tag } => write!(f, "no symbol has the type `{}`", tag),
+            GrammarError::EmptyOnNonEmptyRule { .. } => write!(f, "%empty on non-empty rule"),
+            GrammarError::UnresolvedReference { reference } => {
+                write!(f, "invalid reference `{}`", reference)
+            }
+            GrammarError::AmbiguousReference { reference } => {
+                write!(f, "ambiguous reference `{}`", reference)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Lint::EmptyRule { .. } => write!(f, "empty rule without %empty"),
+            Lint::UnusedToken { name } => write!(f, "token `{}` is unused", name),
+            Lint::UnreachableRule { name } => {
+                write!(f, "nonterminal `{}` is useless in grammar", name)
+            }
+        }
+    }
+}
+
+// nullable: input
+// FIRST(exp) = { NUM '-' }
+// FOLLOW(exp) = { $end '+' }
+impl std::fmt::Display for Analysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbols = self.symbols();
+        let write_set = |f: &mut std::fmt::Formatter<'_>, set: &BTreeSet<SymbolId>| {
+            write!(f, "{{")?;
+            for &symbol in set {
+                write!(f, " {}", symbols.get(symbol).name)?;
+            }
+            writeln!(f, " }}")
+        };
+        write!(f, "nullable:")?;
+        for (id, symbol) in symbols.nonterminals() {
+            if self.is_nullable(id) {
+                write!(f, " {}", symbol.name)?;
+            }
+        }
+        writeln!(f)?;
+        for (id, symbol) in symbols.nonterminals() {
+            write!(f, "FIRST({}) = ", symbol.name)?;
+            write_set(f, self.first(id))?;
+        }
+        for (id, symbol) in symbols.nonterminals() {
+            write!(f, "FOLLOW({}) = ", symbol.name)?;
+            write_set(f, self.follow(id))?;
         }
         Ok(())
     }
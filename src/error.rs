@@ -0,0 +1,162 @@
+use crate::action::Reference;
+use crate::diagnostic::Diagnostic;
+use crate::grammar::Tag;
+use crate::token::Span;
+use crate::token::Spanned;
+use crate::token::Token;
+use crate::token::TokenKind;
+
+#[derive(Debug, Clone)]
+pub enum ParseError {
+    // A token which cannot appear at this position, e.g. `foo bar` where
+    // `foo:` was expected.
+    UnexpectedToken {
+        found: Spanned<Token>,
+        expected: Vec<TokenKind>,
+    },
+    // A `%directive` which is not supported.
+    UnknownDirective {
+        name: Spanned<String>,
+    },
+    // A `{` without its matching `}`.
+    UnterminatedCodeBlock {
+        span: Span,
+    },
+    // A number which does not fit its target type, e.g. `%expect 99999999999`.
+    BadNumber {
+        number: Spanned<String>,
+    },
+    // The input ended in the middle of a declaration or rule.
+    UnexpectedEof {
+        span: Span,
+        expected: Vec<TokenKind>,
+    },
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken { found, .. } => found.span.clone(),
+            ParseError::UnknownDirective { name } => name.span.clone(),
+            ParseError::UnterminatedCodeBlock { span } => span.clone(),
+            ParseError::BadNumber { number } => number.span.clone(),
+            ParseError::UnexpectedEof { span, .. } => span.clone(),
+        }
+    }
+
+    /// The tokens which would have been accepted where the error occurred.
+    pub fn expected(&self) -> &[TokenKind] {
+        match self {
+            ParseError::UnexpectedToken { expected, .. }
+            | ParseError::UnexpectedEof { expected, .. } => expected,
+            _ => &[],
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.to_string(), self.span());
+        match self {
+            ParseError::UnexpectedToken { found, .. } => {
+                diagnostic.with_label(format!("unexpected {}", found.data))
+            }
+            ParseError::UnknownDirective { .. } => diagnostic.with_label("unknown directive"),
+            ParseError::UnterminatedCodeBlock { span } => {
+                Diagnostic::error(self.to_string(), span.start..span.start + 1)
+                    .with_label("unclosed `{` starts here")
+                    .with_help("add a matching `}` to close the block")
+            }
+            ParseError::BadNumber { .. } => diagnostic
+                .with_label("number out of range")
+                .with_note(format!("numbers must be at most {}", i32::MAX)),
+            ParseError::UnexpectedEof { .. } => diagnostic.with_label("input ends here"),
+        }
+    }
+
+    /// The 1-based line and column at which the error starts.
+    pub fn line_col(&self, input: &str) -> (usize, usize) {
+        line_col(input, self.span().start)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// An error in a grammar which parsed successfully.
+#[derive(Debug, Clone)]
+pub enum GrammarError {
+    // `%start foo` where `foo` has no rules.
+    UndefinedStartSymbol { symbol: Spanned<String> },
+    // A grammar without any rules, and hence without a start symbol.
+    NoRules { span: Span },
+    // `a: foo;` or `%destructor { ... } foo` where `foo` is neither a token
+    // nor a rule.
+    UnknownSymbol { symbol: Spanned<String> },
+    // `%printer { ... } <foo>` where no symbol has the type `<foo>`.
+    UnknownTag { tag: Spanned<Tag> },
+    // `a: %empty b;`
+    EmptyOnNonEmptyRule { span: Span },
+    // `$foo` in an action where no symbol is named `foo`.
+    UnresolvedReference { reference: Reference },
+    // `$exp` in `exp: exp '+' exp { ... }`, where several symbols are
+    // named `exp`.
+    AmbiguousReference { reference: Reference },
+}
+
+impl GrammarError {
+    pub fn span(&self) -> Span {
+        match self {
+            GrammarError::UndefinedStartSymbol { symbol } => symbol.span.clone(),
+            GrammarError::NoRules { span } => span.clone(),
+            GrammarError::UnknownSymbol { symbol } => symbol.span.clone(),
+            GrammarError::UnknownTag { tag } => tag.span.clone(),
+            GrammarError::EmptyOnNonEmptyRule { span } => span.clone(),
+            GrammarError::UnresolvedReference { reference } => reference.span.clone(),
+            GrammarError::AmbiguousReference { reference } => reference.span.clone(),
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.to_string(), self.span());
+        match self {
+            GrammarError::UndefinedStartSymbol { .. } => diagnostic
+                .with_label("declared as the start symbol here")
+                .with_help("add a rule for it, or remove the `%start` declaration"),
+            GrammarError::NoRules { .. } => diagnostic,
+            GrammarError::UnknownSymbol { .. } => diagnostic
+                .with_label("not declared")
+                .with_help("declare it with `%token`, or add a rule for it"),
+            GrammarError::UnknownTag { .. } => diagnostic
+                .with_label("unused type")
+                .with_note("tags must appear in a `%token`, `%type` or `%nterm` declaration"),
+            GrammarError::EmptyOnNonEmptyRule { .. } => diagnostic
+                .with_label("the alternative is not empty")
+                .with_help("remove `%empty`"),
+            GrammarError::UnresolvedReference { .. } => diagnostic
+                .with_label("no symbol has this name")
+                .with_help("name the symbol on the right-hand side, e.g. `exp[left]`"),
+            GrammarError::AmbiguousReference { .. } => diagnostic
+                .with_label("refers to several symbols")
+                .with_help("give each symbol its own name, e.g. `exp[left] '+' exp[right]`"),
+        }
+    }
+}
+
+impl std::error::Error for GrammarError {}
+
+/// Converts the byte offset `pos` into a 1-based line and column, where
+/// columns are counted in characters.
+pub fn line_col(input: &str, pos: usize) -> (usize, usize) {
+    let mut line = 1;
+    let mut col = 1;
+    for (i, c) in input.char_indices() {
+        if i >= pos {
+            break;
+        }
+        if c == '\n' {
+            line += 1;
+            col = 1;
+        } else {
+            col += 1;
+        }
+    }
+    (line, col)
+}
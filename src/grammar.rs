@@ -1,13 +1,499 @@
+use crate::action::Reference;
+use crate::action::ReferenceKind;
+use crate::action::Target;
+use crate::error::GrammarError;
+use crate::symbols::SymbolKind;
+use crate::symbols::SymbolTable;
+use crate::token::Span;
+use crate::token::Spanned;
+
 // rule1; rule2; rule3;
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Grammar {
-    pub directives: Vec<Directive>,
+    pub directives: Vec<Spanned<Directive>>,
     pub rules: Vec<Rule>,
-    pub programs: String,
+    // The code after the second `%%`, if there is one
+    pub programs: Option<Spanned<String>>,
+    pub span: Span,
+}
+
+impl Grammar {
+    /// The contents of the `%{ ... %}` blocks, in order of appearance.
+    pub fn prologues(&self) -> impl Iterator<Item = &Spanned<String>> {
+        self.directives
+            .iter()
+            .filter_map(|directive| match &directive.data {
+                Directive::Prologue { code } => Some(code),
+                _ => None,
+            })
+    }
+
+    /// The `%code` blocks with the given qualifier, in order of appearance.
+    /// `None` selects the unqualified `%code { ... }` blocks.
+    pub fn code_blocks<'a>(
+        &'a self,
+        qualifier: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a Spanned<String>> {
+        self.directives
+            .iter()
+            .filter_map(move |directive| match &directive.data {
+                Directive::Code { qualifier: q, code }
+                    if q.as_ref().map(|q| q.data.as_str()) == qualifier =>
+                {
+                    Some(code)
+                }
+                _ => None,
+            })
+    }
+
+    /// The tokens declared by `%token`, in order of declaration.
+    pub fn tokens(&self) -> impl Iterator<Item = &TokenDecl> {
+        self.directives
+            .iter()
+            .filter_map(|directive| match &directive.data {
+                Directive::Token { tokens } => Some(tokens),
+                _ => None,
+            })
+            .flatten()
+    }
+
+    /// The token with the string alias `alias`, which includes the quotes.
+    pub fn token_by_alias(&self, alias: &str) -> Option<&TokenDecl> {
+        self.tokens()
+            .find(|token| token.alias.as_ref().is_some_and(|a| a.data == alias))
+    }
+
+    /// The precedence level and associativity of `symbol`, which may also be
+    /// the string alias of a token. Each `%left`, `%right`, `%nonassoc` and
+    /// `%precedence` declaration introduces a new level, starting at 1, which
+    /// binds tighter than the ones before it.
+    ///
+    /// This builds a [`SymbolTable`] on every call, so callers looking up many
+    /// symbols should build one with [`SymbolTable::new`] and query it instead.
+    pub fn precedence_of(&self, symbol: &str) -> Option<(usize, Associativity)> {
+        let table = SymbolTable::new(self);
+        table.get(table.id(symbol)?).precedence
+    }
+
+    /// The `<type>` of `symbol` as declared by `%type`, `%nterm` or `%token`.
+    /// `symbol` may also be the string alias of a token. If the type is
+    /// declared more than once, the last declaration wins.
+    ///
+    /// Like [`Grammar::precedence_of`], this builds a [`SymbolTable`] on every
+    /// call. [`SymbolInfo::tag`](crate::symbols::SymbolInfo::tag) holds the
+    /// same type without its span.
+    pub fn type_of(&self, symbol: &str) -> Option<&Spanned<Tag>> {
+        let table = SymbolTable::new(self);
+        let id = table.id(symbol)?;
+        let is_symbol = |name: &Spanned<String>| table.id(&name.data) == Some(id);
+        self.directives
+            .iter()
+            .rev()
+            .find_map(|directive| match &directive.data {
+                Directive::Type {
+                    type_name,
+                    rule_names,
+                }
+                | Directive::Nterm {
+                    type_name: Some(type_name),
+                    rule_names,
+                } => rule_names.iter().any(is_symbol).then_some(type_name),
+                Directive::Token { tokens } => tokens
+                    .iter()
+                    .rev()
+                    .find(|token| is_symbol(&token.name))
+                    .and_then(|token| token.tag.as_ref()),
+                _ => None,
+            })
+    }
+
+    /// The symbol declared by `%start`, or otherwise the left-hand side of
+    /// the first rule.
+    pub fn start_symbol(&self) -> Result<&Spanned<String>, GrammarError> {
+        let declared = self
+            .directives
+            .iter()
+            .find_map(|directive| match &directive.data {
+                Directive::Start { symbol } => Some(symbol),
+                _ => None,
+            });
+        match declared {
+            Some(symbol) if self.rule(&symbol.data).is_some() => Ok(symbol),
+            Some(symbol) => Err(GrammarError::UndefinedStartSymbol {
+                symbol: symbol.clone(),
+            }),
+            None => match self.rules.first() {
+                Some(rule) => Ok(&rule.name),
+                None => Err(GrammarError::NoRules {
+                    span: self.span.end..self.span.end,
+                }),
+            },
+        }
+    }
+
+    /// The value of the last `%define name value`, if `name` is defined.
+    /// A definition without a value has the empty keyword as its value.
+    pub fn define(&self, name: &str) -> Option<&DefineValue> {
+        const EMPTY: &DefineValue = &DefineValue::Keyword(String::new());
+        self.directives
+            .iter()
+            .rev()
+            .find_map(|directive| match &directive.data {
+                Directive::Define { name: n, value } if n.data == name => {
+                    Some(value.as_ref().map_or(EMPTY, |value| &value.data))
+                }
+                _ => None,
+            })
+    }
+
+    /// The `api.pure` variable, which is also set by `%pure-parser`.
+    pub fn api_pure(&self) -> Option<ApiPure> {
+        match self.define("api.pure").map(DefineValue::text) {
+            Some("" | "true") => Some(ApiPure::True),
+            Some("false") => Some(ApiPure::False),
+            Some("full") => Some(ApiPure::Full),
+            Some(_) => None,
+            None => self
+                .directives
+                .iter()
+                .any(|directive| matches!(directive.data, Directive::PureParser))
+                .then_some(ApiPure::True),
+        }
+    }
+
+    /// The `parse.error` variable.
+    pub fn parse_error(&self) -> Option<ParseErrorStyle> {
+        match self.define("parse.error")?.text() {
+            "simple" => Some(ParseErrorStyle::Simple),
+            "verbose" => Some(ParseErrorStyle::Verbose),
+            "detailed" => Some(ParseErrorStyle::Detailed),
+            "custom" => Some(ParseErrorStyle::Custom),
+            _ => None,
+        }
+    }
+
+    /// The `lr.type` variable.
+    pub fn lr_type(&self) -> Option<LrType> {
+        match self.define("lr.type")?.text() {
+            "lalr" => Some(LrType::Lalr),
+            "ielr" => Some(LrType::Ielr),
+            "canonical-lr" => Some(LrType::CanonicalLr),
+            _ => None,
+        }
+    }
+
+    /// The `api.value.type` variable, either a keyword such as `union` or
+    /// `variant`, or the C type of semantic values in braces.
+    pub fn api_value_type(&self) -> Option<&DefineValue> {
+        self.define("api.value.type")
+    }
+
+    /// Checks the declarations which refer to other symbols, returning an
+    /// error for each reference which cannot be resolved.
+    pub fn validate(&self) -> Vec<GrammarError> {
+        let mut errors = Vec::new();
+        if let Err(error) = self.start_symbol() {
+            errors.push(error);
+        }
+        for alternative in self.rules.iter().flat_map(|rule| &rule.alternatives) {
+            if let Some(empty) = alternative
+                .empty
+                .as_ref()
+                .filter(|_| !alternative.is_empty())
+            {
+                errors.push(GrammarError::EmptyOnNonEmptyRule {
+                    span: empty.clone(),
+                });
+            }
+        }
+        for rule in &self.rules {
+            for alternative in &rule.alternatives {
+                errors.extend(rule.resolve_named_refs(alternative));
+            }
+        }
+        let table = SymbolTable::new(self);
+        for (_, symbol) in table.undefined() {
+            for span in &symbol.uses {
+                errors.push(GrammarError::UnknownSymbol {
+                    symbol: Spanned::new(symbol.name.clone(), span.clone()),
+                });
+            }
+        }
+        for directive in &self.directives {
+            let (Directive::Destructor { symbols, .. } | Directive::Printer { symbols, .. }) =
+                &directive.data
+            else {
+                continue;
+            };
+            for symbol in symbols {
+                match &symbol.data {
+                    SymbolOrTag::Symbol(name) if !Self::declares_symbol(&table, name) => {
+                        errors.push(GrammarError::UnknownSymbol {
+                            symbol: Spanned::new(name.clone(), symbol.span.clone()),
+                        });
+                    }
+                    SymbolOrTag::Tag(tag) if !Self::declares_tag(&table, tag) => {
+                        errors.push(GrammarError::UnknownTag {
+                            tag: Spanned::new(tag.clone(), symbol.span.clone()),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+        errors
+    }
+
+    // Whether `name` is a token, a nonterminal, or a character literal. Like
+    // in rules, tokens may also be referred to by their string alias.
+    fn declares_symbol(table: &SymbolTable, name: &str) -> bool {
+        if name.starts_with('\'') || name == "error" {
+            return true;
+        }
+        table
+            .id(name)
+            .is_some_and(|id| table.get(id).kind != SymbolKind::Undefined)
+    }
+
+    // Whether some symbol is declared with the type `tag`. The tags `<*>` and
+    // `<>`, which select all typed and all untyped symbols, always exist.
+    fn declares_tag(table: &SymbolTable, tag: &Tag) -> bool {
+        if let Tag::Any | Tag::Untyped = tag {
+            return true;
+        }
+        table
+            .iter()
+            .any(|(_, symbol)| symbol.tag.as_ref() == Some(tag))
+    }
+
+    /// Replaces every mid-rule action by a fresh nonterminal with a single
+    /// `%empty` alternative performing the action, as Bison does. For example
+    /// `a: b { x } c { y };` becomes `$@1: %empty { x }; a: b $@1 c { y };`.
+    /// The new rules are numbered in order of appearance and placed before
+    /// the rule which contained the action. Like in Bison, actions whose
+    /// value is used, through `$$` or a later `$N`, are named `@N` instead.
+    ///
+    /// References in a moved action to the symbols before it are rewritten
+    /// relative to the top of the stack, e.g. `$1` in `a: b c { $1 }` becomes
+    /// `$-1`, and `$name` likewise. Such values are given the declared type
+    /// of their symbol, as in `$<ival>-1`. If the grammar has no `%start`, one
+    /// is added so that the start symbol stays the same.
+    pub fn desugar_mid_rule_actions(&self) -> Grammar {
+        let table = SymbolTable::new(self);
+        let mut count = 0;
+        let mut rules = Vec::new();
+        for rule in &self.rules {
+            let mut rule = rule.clone();
+            for alternative in &mut rule.alternatives {
+                for index in 0..alternative.items.len() {
+                    if !matches!(alternative.items[index], Item::Action(_)) {
+                        continue;
+                    }
+                    let action = Self::lift_mid_rule_action(
+                        &table,
+                        &rule.name,
+                        &rule.named_ref,
+                        alternative,
+                        index,
+                    );
+                    count += 1;
+                    let name = if Self::uses_mid_rule_value(alternative, index) {
+                        format!("@{count}")
+                    } else {
+                        format!("$@{count}")
+                    };
+                    let name = Spanned::new(name, action.span.clone());
+                    rules.push(Rule {
+                        name: name.clone(),
+                        named_ref: None,
+                        alternatives: vec![Alternative {
+                            items: Vec::new(),
+                            empty: Some(action.span.start..action.span.start),
+                            precedence: None,
+                            action: Some(action.clone()),
+                            span: action.span.clone(),
+                        }],
+                        span: action.span.clone(),
+                    });
+                    alternative.items[index] = Item::Symbol {
+                        symbol: name,
+                        named_ref: None,
+                    };
+                }
+            }
+            rules.push(rule);
+        }
+
+        let mut directives = self.directives.clone();
+        let declared = directives
+            .iter()
+            .any(|directive| matches!(directive.data, Directive::Start { .. }));
+        if let (false, Ok(start)) = (declared, self.start_symbol()) {
+            directives.push(Spanned::new(
+                Directive::Start {
+                    symbol: start.clone(),
+                },
+                start.span.clone(),
+            ));
+        }
+        Grammar {
+            directives,
+            rules,
+            ..self.clone()
+        }
+    }
+
+    // Returns the mid-rule action at `index` in the items of `alternative`,
+    // with its references to earlier symbols made relative to the top of the
+    // stack. `$0` is then the symbol just before the action.
+    fn lift_mid_rule_action(
+        table: &SymbolTable,
+        lhs: &Symbol,
+        lhs_ref: &Option<Spanned<String>>,
+        alternative: &Alternative,
+        index: usize,
+    ) -> Spanned<String> {
+        let Item::Action(action) = &alternative.items[index] else {
+            unreachable!("not a mid-rule action");
+        };
+        let mut code = String::new();
+        let mut copied = action.span.start;
+        for reference in crate::action::references(action) {
+            // The 1-based position which is referred to
+            let position = match &reference.target {
+                Target::Index(position) if *position <= index as i32 => *position,
+                Target::Name(name) => {
+                    let mut positions = (0..=index).filter(|&position| match position {
+                        0 => is_named(lhs, lhs_ref, name),
+                        _ => match &alternative.items[position - 1] {
+                            Item::Symbol { symbol, named_ref } => is_named(symbol, named_ref, name),
+                            Item::Action(_) => false,
+                        },
+                    });
+                    match (positions.next(), positions.next()) {
+                        (Some(position), None) if position > 0 => position as i32,
+                        // `$lhs`, ambiguous and unresolved references are
+                        // left as they are
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            };
+            let item = usize::try_from(position - 1)
+                .ok()
+                .and_then(|i| alternative.items.get(i));
+            let symbol = match item {
+                Some(Item::Symbol { symbol, .. }) => Some(symbol),
+                _ => None,
+            };
+            let tag = match reference.kind {
+                ReferenceKind::Value => reference.tag.clone().or_else(|| {
+                    symbol
+                        .and_then(|symbol| table.id(&symbol.data))
+                        .and_then(|id| table.get(id).tag.clone())
+                }),
+                ReferenceKind::Location => None,
+            };
+            let lifted = Reference {
+                target: Target::Index(position - index as i32),
+                tag,
+                ..reference.clone()
+            };
+            let start = reference.span.start - action.span.start;
+            code.push_str(&action.data[copied - action.span.start..start]);
+            code.push_str(&lifted.to_string());
+            copied = reference.span.end;
+        }
+        code.push_str(&action.data[copied - action.span.start..]);
+        Spanned::new(code, action.span.clone())
+    }
+
+    // Whether the value of the mid-rule action at `index` in the items of
+    // `alternative` is used, either by setting `$$` inside it or by a later
+    // `$N`.
+    fn uses_mid_rule_value(alternative: &Alternative, index: usize) -> bool {
+        let Some(Item::Action(action)) = alternative.items.get(index) else {
+            return false;
+        };
+        let position = index as i32 + 1;
+        let later = alternative.items[index + 1..]
+            .iter()
+            .filter_map(|item| match item {
+                Item::Action(action) => Some(action),
+                Item::Symbol { .. } => None,
+            });
+        crate::action::references(action)
+            .iter()
+            .any(|reference| is_value(reference, &Target::Lhs))
+            || later
+                .chain(&alternative.action)
+                .flat_map(crate::action::references)
+                .any(|reference| is_value(&reference, &Target::Index(position)))
+    }
+
+    /// The rule with the left-hand side `name`.
+    pub fn rule(&self, name: &str) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.name.data == name)
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Directive {
+    // %{ code %}
+    // ------------
+    // C code copied verbatim to the beginning of the generated parser,
+    // typically #includes and declarations used by the actions. A grammar
+    // may contain several prologue blocks, which are emitted in order.
+    Prologue {
+        code: Spanned<String>,
+    },
+    // %start symbol
+    // ------------
+    // Declares the nonterminal which the parser should recognize. Defaults
+    // to the left-hand side of the first rule.
+    Start {
+        symbol: Spanned<String>,
+    },
+    // %define variable [value]
+    // ------------
+    // Sets a Bison variable which tunes the generated parser, e.g.
+    // `%define api.pure full` or `%define api.value.type {struct foo}`.
+    Define {
+        name: Spanned<String>,
+        value: Option<Spanned<DefineValue>>,
+    },
+    // %code [qualifier] { code }
+    // ------------
+    // C code inserted into the generated parser at a location given by the
+    // qualifier: `requires` and `provides` go in the header before and after
+    // YYSTYPE, `top` at the very top, and `imports` is for Java parsers.
+    // Unqualified code goes into the implementation file.
+    Code {
+        qualifier: Option<Spanned<String>>,
+        code: Spanned<String>,
+    },
+    // %destructor { code } symbols
+    // ------------
+    // Code run to free the semantic value of a discarded symbol, e.g. when
+    // popping the stack during error recovery. The symbols may be names,
+    // character literals or <tag>s, which select every symbol of that type.
+    Destructor {
+        code: Spanned<String>,
+        symbols: Vec<Spanned<SymbolOrTag>>,
+    },
+    // %printer { code } symbols
+    // ------------
+    // Code run to print the semantic value of a symbol in debug traces.
+    Printer {
+        code: Spanned<String>,
+        symbols: Vec<Spanned<SymbolOrTag>>,
+    },
+    // %initial-action { code }
+    // ------------
+    // Code run before parsing starts, e.g. to initialize the location.
+    InitialAction {
+        code: Spanned<String>,
+    },
     // %pure-parser
     // ------------
     // Tells yacc (or Bison) to generate a pure parser, which means that the parser
@@ -22,7 +508,7 @@ pub enum Directive {
     // tells yacc the expected number of	shift/reduce conflicts. That
     // makes it only report the number if it differs.
     Expect {
-        number: i32,
+        number: Spanned<i32>,
     },
     // %name-prefix="prefix"
     // ------------
@@ -31,7 +517,7 @@ pub enum Directive {
     // of symbols renamed is yyparse, yylex, yyerror,  yylval,
     // yychar, and yydebug.
     NamePrefix {
-        prefix: String,
+        prefix: Spanned<String>,
     },
     // %locations
     // ------------
@@ -41,50 +527,257 @@ pub enum Directive {
     // ------------
     // Adds a parameter to the yyparse function signature.
     ParseParam {
-        params: String,
+        params: Spanned<String>,
     },
     // %lex-param { params }
     // Adds a parameter to the yylex function signature.
     LexProgram {
-        params: String,
+        params: Spanned<String>,
     },
     // %union { code }
     Union {
-        code: String,
+        code: Spanned<String>,
     },
     // %type <type> identifiers
     Type {
-        type_name: String,
-        rule_names: Vec<String>,
+        type_name: Spanned<Tag>,
+        rule_names: Vec<Spanned<String>>,
     },
-    // %token [<token>] identifiers
+    // %token [<tag>] name [number] ["alias"] ... [<tag>] ...
     Token {
-        token_name: Option<String>,
-        rule_names: Vec<String>,
+        tokens: Vec<TokenDecl>,
     },
     // %left identifiers
     Left {
-        rule_names: Vec<String>,
+        rule_names: Vec<Spanned<String>>,
     },
     // %right identifiers
     Right {
-        rule_names: Vec<String>,
+        rule_names: Vec<Spanned<String>>,
     },
     // %nonassoc identifiers
     NonAssoc {
-        rule_names: Vec<String>,
+        rule_names: Vec<Spanned<String>>,
     },
+    // %precedence identifiers
+    // ------------
+    // Gives the tokens a precedence level without an associativity, so that
+    // they may only be used to resolve conflicts between different levels.
+    Precedence {
+        rule_names: Vec<Spanned<String>>,
+    },
+    // %nterm [<type>] identifiers
+    // ------------
+    // Declares nonterminals, optionally with the type of their values.
+    Nterm {
+        type_name: Option<Spanned<Tag>>,
+        rule_names: Vec<Spanned<String>>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    // %left
+    Left,
+    // %right
+    Right,
+    // %nonassoc
+    NonAssoc,
+    // %precedence
+    None,
+}
+
+// %token <ival> INT 258 "integer"
+#[derive(Debug, Clone)]
+pub struct TokenDecl {
+    pub name: Spanned<String>,
+    // The token code, which is otherwise assigned by the parser generator
+    pub code: Option<Spanned<i32>>,
+    // A string literal, including the quotes, which may be used instead of
+    // the name in rules
+    pub alias: Option<Spanned<String>>,
+    pub tag: Option<Spanned<Tag>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolOrTag {
+    // foo or 'c'
+    Symbol(String),
+    // <tag>
+    Tag(Tag),
+}
+
+// The type of a semantic value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tag {
+    // <ival>, <std::string> or <struct foo *>, without the angle brackets
+    Named(String),
+    // <*>, every symbol which has a type
+    Any,
+    // <>, every symbol which has no type
+    Untyped,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefineValue {
+    // %define parse.error verbose
+    Keyword(String),
+    // %define api.prefix "foo", without the quotes
+    String(String),
+    // %define api.value.type {struct foo}, without the braces
+    Code(String),
+}
+
+impl DefineValue {
+    pub fn text(&self) -> &str {
+        match self {
+            DefineValue::Keyword(text) | DefineValue::String(text) | DefineValue::Code(text) => {
+                text
+            }
+        }
+    }
+}
+
+// %define api.pure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiPure {
+    False,
+    True,
+    // Also passes locations and parse parameters to yyerror
+    Full,
+}
+
+// %define parse.error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorStyle {
+    // "syntax error"
+    Simple,
+    // "syntax error, unexpected X, expecting Y or Z"
+    Verbose,
+    // Like verbose, but with translatable token names
+    Detailed,
+    // Reported by a user-defined yyreport_syntax_error
+    Custom,
+}
+
+// %define lr.type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LrType {
+    Lalr,
+    Ielr,
+    CanonicalLr,
 }
 
 // foo: bar baz { ... } | qux { ...};
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Rule {
-    pub name: String,
+    pub name: Spanned<String>,
+    // exp[result]:, the name by which actions may refer to the left-hand side
+    pub named_ref: Option<Spanned<String>>,
     pub alternatives: Vec<Alternative>,
+    pub span: Span,
 }
 
-#[derive(Debug)]
+impl Rule {
+    // Checks that every `$name` in the actions of `alternative` refers to
+    // exactly one symbol. Mid-rule actions can only refer to the symbols
+    // before them.
+    fn resolve_named_refs(&self, alternative: &Alternative) -> Vec<GrammarError> {
+        let mut names = vec![(&self.name, &self.named_ref)];
+        let mut errors = Vec::new();
+        let actions = alternative.items.iter().map(Some).chain([None]);
+        for item in actions {
+            let action = match item {
+                Some(Item::Symbol { symbol, named_ref }) => {
+                    names.push((symbol, named_ref));
+                    continue;
+                }
+                Some(Item::Action(action)) => action,
+                None => match &alternative.action {
+                    Some(action) => action,
+                    None => continue,
+                },
+            };
+            for reference in crate::action::references(action) {
+                let Target::Name(name) = &reference.target else {
+                    continue;
+                };
+                let matches = names
+                    .iter()
+                    .filter(|(symbol, named_ref)| is_named(symbol, named_ref, name))
+                    .count();
+                match matches {
+                    0 => errors.push(GrammarError::UnresolvedReference { reference }),
+                    1 => {}
+                    _ => errors.push(GrammarError::AmbiguousReference { reference }),
+                }
+            }
+        }
+        errors
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Alternative {
-    pub elements: Vec<String>,
-    pub action: Option<String>,
+    // The right-hand side, which may contain mid-rule actions
+    pub items: Vec<Item>,
+    // The span of `%empty`, which marks the alternative as intentionally empty
+    pub empty: Option<Span>,
+    // %prec symbol, which gives the alternative the precedence of `symbol`
+    pub precedence: Option<Symbol>,
+    // The action at the end of the right-hand side
+    pub action: Option<Spanned<String>>,
+    pub span: Span,
+}
+
+impl Alternative {
+    /// Whether the right-hand side derives the empty string directly, i.e.
+    /// has neither symbols nor mid-rule actions.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The symbols of the right-hand side, skipping mid-rule actions.
+    pub fn symbols(&self) -> impl Iterator<Item = &Symbol> {
+        self.items.iter().filter_map(|item| match item {
+            Item::Symbol { symbol, .. } => Some(symbol),
+            Item::Action(_) => None,
+        })
+    }
+
+    /// The `$$`, `$1`, `@2`, `$name`, ... references in the final action.
+    pub fn action_references(&self) -> Vec<Reference> {
+        self.action
+            .as_ref()
+            .map_or_else(Vec::new, crate::action::references)
+    }
+}
+
+// Whether `$name` refers to `symbol`. A symbol with an explicit name, e.g.
+// `exp[left]`, can only be referred to by that name.
+fn is_named(symbol: &Symbol, named_ref: &Option<Spanned<String>>, name: &str) -> bool {
+    match named_ref {
+        Some(named_ref) => named_ref.data == name,
+        None => symbol.data == name,
+    }
+}
+
+fn is_value(reference: &Reference, target: &Target) -> bool {
+    reference.kind == ReferenceKind::Value && reference.target == *target
 }
+
+// a: b { x } c { y };
+//    ^ ^^^^^ ^
+#[derive(Debug, Clone)]
+pub enum Item {
+    Symbol {
+        symbol: Symbol,
+        // exp[left], the name by which actions may refer to the symbol
+        named_ref: Option<Spanned<String>>,
+    },
+    // A mid-rule action
+    Action(Spanned<String>),
+}
+
+// The name of a symbol as written, e.g. `expr` or `'+'`
+pub type Symbol = Spanned<String>;
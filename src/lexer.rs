@@ -1,195 +1,328 @@
 use crate::token::Spanned;
 use crate::token::Token;
 
+// Positions are byte offsets into `input`, and always lie on character
+// boundaries.
 #[derive(Debug)]
 pub struct Lexer<'a> {
     input: &'a str,
     pos: usize,
+    // Number of `%%` separators seen so far
+    sections: usize,
+    eof: bool,
 }
 
 impl<'a> Iterator for Lexer<'a> {
     type Item = Spanned<Token>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut chars = self.input[self.pos..].chars();
-        let mut start = self.pos;
-        let token = loop {
-            let c = chars.next()?;
-            match c {
-                '\'' => {
-                    self.advance();
-                    chars.next().expect("unexpected end of input");
-                    self.advance();
-                    let c = chars.next().expect("unexpected end of input");
-                    self.advance();
-                    if c == '\'' {
-                        break Token::Char;
-                    }
-                    break Token::Err;
+        // Everything after the second `%%` is C code which is not lexed
+        if self.sections == 2 {
+            self.sections += 1;
+            let start = self.pos;
+            self.pos = self.input.len();
+            return Some(Spanned::new(Token::Epilogue, start..self.pos));
+        }
+        loop {
+            let start = self.pos;
+            let Some(c) = self.bump() else {
+                if self.eof {
+                    return None;
                 }
-                // '<char>'
-                '/' => {
-                    self.advance();
-                    match chars.next()? {
-                        '/' => {
-                            self.advance();
-                            while let Some(c) = chars.next() {
-                                if c == '\n' {
-                                    break;
-                                }
-                                self.advance();
-                            }
-                            start = self.pos;
-                            continue;
-                        }
-                        '*' => {
-                            self.advance();
-                            loop {
-                                let c = chars.next().expect("unexpected end of input");
-                                self.advance();
-                                if c == '*' {
-                                    let c = chars.next().expect("unexpected end of input");
-                                    if c == '/' {
-                                        self.advance();
-                                        break;
-                                    } else {
-                                        chars = self.input[self.pos..].chars();
-                                    }
-                                }
-                            }
-                            start = self.pos;
-                            continue;
-                        }
-                        _ => break Token::Err,
+                self.eof = true;
+                return Some(Spanned::new(Token::Eof, start..start));
+            };
+            let token = match c {
+                c if c.is_whitespace() => continue,
+                // 'a', '\n' or '\x41'
+                '\'' => self.lex_char(),
+                '/' => match self.peek() {
+                    Some('/') => {
+                        self.skip_past("\n");
+                        continue;
                     }
-                }
-                '\n' | ' ' | '\t' => {
-                    self.advance();
-                    start = self.pos;
-                    continue;
-                }
-                '=' => {
-                    self.advance();
-                    break Token::Equal;
-                }
-                '0'..='9' => {
-                    self.advance();
-                    while let Some(c) = chars.next() {
-                        if c.is_ascii_digit() {
-                            self.advance();
+                    Some('*') => {
+                        self.bump();
+                        if self.skip_past("*/") {
                             continue;
                         }
-                        break;
+                        Token::Err
                     }
-                    break Token::Number;
+                    _ => Token::Err,
+                },
+                '=' => Token::Equal,
+                '0'..='9' => {
+                    self.bump_while(|c| c.is_ascii_digit());
+                    Token::Number
                 }
-                '"' => {
-                    self.advance();
-                    while let Some(c) = chars.next() {
-                        self.advance();
-                        if c == '"' {
-                            break;
+                '"' => self.lex_string(),
+                '%' => match self.peek() {
+                    Some('%') => {
+                        self.bump();
+                        self.sections += 1;
+                        Token::PercentPercent
+                    }
+                    // %{ ... %}
+                    Some('{') => {
+                        self.bump();
+                        if self.skip_past("%}") {
+                            Token::Prologue
+                        } else {
+                            Token::UnterminatedCode
                         }
                     }
-                    break Token::String;
+                    Some('a'..='z' | 'A'..='Z') => {
+                        self.bump_while(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+                        Token::Directive
+                    }
+                    _ => Token::Err,
+                },
+                '|' => Token::Bar,
+                ':' => Token::Colon,
+                ';' => Token::SemiColon,
+                // {...}
+                '{' => self.lex_code(),
+                'a'..='z' | 'A'..='Z' | '_' => {
+                    // Bison allows dots and dashes, e.g. `api.value.type`
+                    self.bump_while(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+                    Token::Ident
                 }
-                '%' => {
-                    self.advance();
-                    match chars.next()? {
-                        '%' => {
-                            self.advance();
-                            break Token::PercentPercent;
-                        }
-                        'a'..='z' | 'A'..='Z' => {
-                            self.advance();
-                            while let Some(c) = chars.next() {
-                                if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
-                                    self.advance();
-                                    continue;
-                                }
-                                break;
-                            }
-                            break Token::Directive;
-                        }
-                        _ => {
-                            self.advance();
-                            break Token::Err;
-                        }
+                // <tag>, <*>, <>, <std::vector<int>> or <struct foo *>
+                '<' => self.lex_tag(),
+                // exp[left]
+                '[' => {
+                    self.bump_while(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+                    if self.pos > start + 1 && self.peek() == Some(']') {
+                        self.bump();
+                        Token::NamedRef
+                    } else {
+                        Token::Err
                     }
                 }
-                '|' => {
-                    self.advance();
-                    break Token::Bar;
+                _ => Token::Err,
+            };
+            return Some(Spanned::new(token, start..self.pos));
+        }
+    }
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Lexer {
+            input,
+            pos: 0,
+            sections: 0,
+            eof: false,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn bump_while(&mut self, f: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&f) {
+            self.bump();
+        }
+    }
+
+    // Moves past the next occurrence of `end`, or to the end of the input if
+    // there is none. Returns whether `end` was found.
+    fn skip_past(&mut self, end: &str) -> bool {
+        match self.input[self.pos..].find(end) {
+            Some(i) => {
+                self.pos += i + end.len();
+                true
+            }
+            None => {
+                self.pos = self.input.len();
+                false
+            }
+        }
+    }
+
+    // Lexes a character literal, starting after its opening quote
+    fn lex_char(&mut self) -> Token {
+        let rest = &self.input[self.pos..];
+        if let Some((c, len)) = unescape(rest) {
+            if rest[len..].starts_with('\'') {
+                self.pos += len + 1;
+                return Token::Char(c);
+            }
+        }
+        // Skip to the closing quote, if it is on the same line
+        let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+        self.pos += line.find('\'').map_or(line.len(), |i| i + 1);
+        Token::Err
+    }
+
+    // Lexes a string literal, starting after its opening quote
+    fn lex_string(&mut self) -> Token {
+        loop {
+            match self.bump() {
+                Some('"') => return Token::String,
+                Some('\\') => {
+                    self.bump();
                 }
-                ':' => {
-                    self.advance();
-                    break Token::Colon;
+                Some('\n') | None => return Token::Err,
+                Some(_) => {}
+            }
+        }
+    }
+
+    // Lexes a code block, starting after its opening brace. Braces inside C
+    // string literals, character literals and comments are not counted.
+    fn lex_code(&mut self) -> Token {
+        let mut depth = 1;
+        loop {
+            match self.bump() {
+                Some('{') => depth += 1,
+                Some('}') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Token::Code;
+                    }
                 }
-                ';' => {
-                    self.advance();
-                    break Token::SemiColon;
+                Some(quote @ ('"' | '\'')) => {
+                    self.pos += literal_len(&self.input[self.pos..], quote);
                 }
-                // {...}
-                '{' => {
-                    self.advance();
-                    let mut depth = 1;
-                    loop {
-                        let c = chars.next().expect("unexpected end of input");
-                        self.advance();
-                        match c {
-                            '{' => depth += 1,
-                            '}' => {
-                                depth -= 1;
-                                if depth == 0 {
-                                    break;
-                                }
-                            }
-                            _ => {}
-                        }
+                Some('/') => match self.peek() {
+                    Some('/') => {
+                        self.skip_past("\n");
                     }
-                    break Token::Code;
-                }
-                'a'..='z' | 'A'..='Z' => {
-                    self.advance();
-                    while let Some(c) = chars.next() {
-                        match c {
-                            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => {
-                                self.advance();
-                                continue;
-                            }
-                            _ => break,
+                    Some('*') => {
+                        self.bump();
+                        if !self.skip_past("*/") {
+                            return Token::UnterminatedCode;
                         }
                     }
-                    break Token::Ident;
-                }
-                '<' => {
-                    self.advance();
-                    break loop {
-                        let c = chars.next().expect("unexpected end of input");
-                        self.advance();
-                        match c {
-                            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => continue,
-                            '>' => break Token::Type,
-                            _ => break Token::Err,
-                        }
-                    };
-                }
-                _ => {
-                    self.advance();
-                    Token::Err
-                }
-            };
-        };
-        Some(Spanned::new(token, start..self.pos))
+                    _ => {}
+                },
+                Some(_) => {}
+                None => return Token::UnterminatedCode,
+            }
+        }
+    }
+
+    // Lexes a type tag, starting after its opening angle bracket
+    fn lex_tag(&mut self) -> Token {
+        match tag_len(&self.input[self.pos..]) {
+            Ok(len) => {
+                self.pos += len;
+                Token::Type
+            }
+            Err(len) => {
+                self.pos += len;
+                Token::Err
+            }
+        }
     }
 }
 
-impl<'a> Lexer<'a> {
-    pub fn new(input: &'a str) -> Self {
-        Lexer { input, pos: 0 }
+// Returns the length in bytes of the C string or character literal at the
+// start of `s`, which begins after its opening quote. An unterminated literal
+// ends at the end of its line, so that a stray quote such as the one in `$x's`
+// does not swallow the rest of the code.
+pub(crate) fn literal_len(s: &str, quote: char) -> usize {
+    let mut len = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            break;
+        }
+        len += c.len_utf8();
+        if c == quote {
+            break;
+        }
+        if c == '\\' {
+            len += chars.next().map_or(0, char::len_utf8);
+        }
     }
+    len
+}
 
-    fn advance(&mut self) {
-        self.pos += 1;
+// Returns the length in bytes of the type tag at the start of `s`, which
+// begins after its opening angle bracket, up to and including its closing
+// one. A tag which is not closed before the end of the statement is an error
+// carrying the length up to and including the character which ended it.
+pub(crate) fn tag_len(s: &str) -> Result<usize, usize> {
+    let mut depth = 1;
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(i + 1);
+                }
+            }
+            // `->` does not close the tag
+            '-' if chars.peek().is_some_and(|&(_, c)| c == '>') => {
+                chars.next();
+            }
+            '\n' | ';' | '{' | '}' => return Err(i + 1),
+            _ => {}
+        }
     }
+    Err(s.len())
+}
+
+// Decodes the character or C escape sequence at the start of `s`, returning
+// it together with its length in bytes.
+pub(crate) fn unescape(s: &str) -> Option<(char, usize)> {
+    let mut chars = s.chars();
+    let c = chars.next()?;
+    match c {
+        '\\' => {}
+        '\'' | '\n' => return None,
+        _ => return Some((c, c.len_utf8())),
+    }
+    let e = chars.next()?;
+    let c = match e {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        'a' => '\x07',
+        'b' => '\x08',
+        'f' => '\x0c',
+        'v' => '\x0b',
+        '\\' | '\'' | '"' | '?' => e,
+        // \ooo
+        '0'..='7' => {
+            let digits = s[1..]
+                .bytes()
+                .take(3)
+                .take_while(|b| (b'0'..=b'7').contains(b))
+                .count();
+            let value = u32::from_str_radix(&s[1..1 + digits], 8).ok()?;
+            return Some((char::from_u32(value)?, 1 + digits));
+        }
+        // \xhh..., \uhhhh and \Uhhhhhhhh
+        'x' | 'u' | 'U' => {
+            let max = match e {
+                'x' => usize::MAX,
+                'u' => 4,
+                _ => 8,
+            };
+            let digits = s[2..]
+                .bytes()
+                .take(max)
+                .take_while(u8::is_ascii_hexdigit)
+                .count();
+            if digits == 0 || (e != 'x' && digits != max) {
+                return None;
+            }
+            let value = u32::from_str_radix(&s[2..2 + digits], 16).ok()?;
+            return Some((char::from_u32(value)?, 2 + digits));
+        }
+        _ => return None,
+    };
+    Some((c, 2))
 }
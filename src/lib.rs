@@ -0,0 +1,31 @@
+pub mod action;
+pub mod analysis;
+pub mod diagnostic;
+mod display;
+pub mod error;
+pub mod grammar;
+pub mod lexer;
+pub mod lint;
+pub mod parser;
+pub mod symbols;
+pub mod token;
+
+use error::ParseError;
+use grammar::Grammar;
+use lexer::Lexer;
+use parser::Parser;
+
+/// Parses a yacc grammar from `input`.
+pub fn parse(input: &str) -> Result<Grammar, ParseError> {
+    let lexer = Lexer::new(input);
+    let mut parser = Parser::new(input, lexer);
+    parser.parse_grammar()
+}
+
+/// Parses a yacc grammar from `input`, recovering from errors. Returns the
+/// partial grammar together with every error which was encountered.
+pub fn parse_with_recovery(input: &str) -> (Grammar, Vec<ParseError>) {
+    let lexer = Lexer::new(input);
+    let mut parser = Parser::new(input, lexer);
+    parser.parse_grammar_with_recovery()
+}
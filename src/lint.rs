@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+
+use crate::diagnostic::Diagnostic;
+use crate::grammar::Grammar;
+use crate::symbols::SymbolTable;
+use crate::token::Span;
+use crate::token::Spanned;
+
+// A warning about a construct which is valid, but likely a mistake.
+#[derive(Debug, Clone)]
+pub enum Lint {
+    // `a: | b;` where the empty alternative is not marked with `%empty`,
+    // like Bison's -Wempty-rule.
+    EmptyRule { span: Span },
+    // `%token FOO` where `FOO` appears in no rule.
+    UnusedToken { name: Spanned<String> },
+    // A rule which cannot be derived from the start symbol.
+    UnreachableRule { name: Spanned<String> },
+}
+
+impl Lint {
+    pub fn span(&self) -> Span {
+        match self {
+            Lint::EmptyRule { span } => span.clone(),
+            Lint::UnusedToken { name } => name.span.clone(),
+            Lint::UnreachableRule { name } => name.span.clone(),
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::warning(self.to_string(), self.span());
+        match self {
+            Lint::EmptyRule { .. } => diagnostic
+                .with_label("empty alternative")
+                .with_help("add `%empty` to mark the alternative as intentionally empty"),
+            Lint::UnusedToken { .. } => diagnostic
+                .with_label("declared here")
+                .with_help("remove the declaration, or use the token in a rule"),
+            Lint::UnreachableRule { .. } => diagnostic
+                .with_label("defined here")
+                .with_note("no derivation of the start symbol contains this nonterminal"),
+        }
+    }
+}
+
+/// Checks `grammar` for likely mistakes.
+pub fn lint(grammar: &Grammar) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    for rule in &grammar.rules {
+        for alternative in &rule.alternatives {
+            if alternative.is_empty() && alternative.empty.is_none() {
+                lints.push(Lint::EmptyRule {
+                    span: alternative.span.clone(),
+                });
+            }
+        }
+    }
+
+    let table = SymbolTable::new(grammar);
+    for token in grammar.tokens() {
+        if table
+            .id(&token.name.data)
+            .is_some_and(|id| table.get(id).uses.is_empty())
+        {
+            lints.push(Lint::UnusedToken {
+                name: token.name.clone(),
+            });
+        }
+    }
+
+    // Walk the rules from the start symbol
+    let Ok(start) = grammar.start_symbol() else {
+        return lints;
+    };
+    let mut reachable = HashSet::from([start.data.as_str()]);
+    let mut stack = vec![start.data.as_str()];
+    while let Some(name) = stack.pop() {
+        let rules = grammar.rules.iter().filter(|rule| rule.name.data == name);
+        for alternative in rules.flat_map(|rule| &rule.alternatives) {
+            for symbol in alternative.symbols() {
+                if reachable.insert(&symbol.data) {
+                    stack.push(&symbol.data);
+                }
+            }
+        }
+    }
+    for rule in &grammar.rules {
+        if !reachable.contains(rule.name.data.as_str()) {
+            lints.push(Lint::UnreachableRule {
+                name: rule.name.clone(),
+            });
+        }
+    }
+    lints
+}
@@ -1,49 +1,74 @@
-pub mod display;
-pub mod grammar;
-pub mod lexer;
-pub mod parser;
-pub mod token;
+use std::io::IsTerminal;
 
-use lexer::Lexer;
+use yacc_parser::analysis::Analysis;
+use yacc_parser::diagnostic::Diagnostic;
+use yacc_parser::error::ParseError;
+use yacc_parser::lexer::Lexer;
+use yacc_parser::token;
 
 fn main() {
-    let input_file = std::env::args().nth(1).expect("No input file");
-    let input = std::fs::read_to_string(input_file).expect("Failed to read input file");
+    // --sets prints the nullable nonterminals and the FIRST and FOLLOW sets
+    let dump_sets = std::env::args().skip(1).any(|arg| arg == "--sets");
+    let input_file = std::env::args()
+        .skip(1)
+        .find(|arg| !arg.starts_with("--"))
+        .expect("No input file");
+    let input = std::fs::read_to_string(&input_file).expect("Failed to read input file");
+    let color = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
 
     // Testing lexer
     let lexer = Lexer::new(&input);
+    let mut failed = false;
     for t in lexer {
-        if t.data == token::Token::Err {
-            let (line0, col0) = line_col(&input, t.span.start);
-            let (line1, col1) = line_col(&input, t.span.end);
-            panic!(
-                r#"Un-scannable token at {}:{}..{}:{}: "{}""#,
-                line0,
-                col0,
-                line1,
-                col1,
-                &input[t.span.clone()]
-            );
-        }
-        println!("{:?}", t);
+        let diagnostic = match t.data {
+            token::Token::UnterminatedCode => {
+                ParseError::UnterminatedCodeBlock { span: t.span }.to_diagnostic()
+            }
+            token::Token::Err => Diagnostic::error(
+                format!("un-scannable token `{}`", &input[t.span.clone()]),
+                t.span,
+            )
+            .with_label("not a valid token"),
+            _ => {
+                // The sets are printed to stdout instead
+                if !dump_sets {
+                    println!("{:?}", t);
+                }
+                continue;
+            }
+        };
+        eprintln!("{}", diagnostic.render(&input, &input_file, color));
+        failed = true;
+    }
+    if failed {
+        std::process::exit(1);
     }
 
-    let lexer = Lexer::new(&input);
-    let mut parser = parser::Parser::new(&input, lexer);
-    let _grammar = parser.parse_grammar();
-    // println!("{}", grammar);
-}
+    let (grammar, errors) = yacc_parser::parse_with_recovery(&input);
+    for error in &errors {
+        let diagnostic = error.to_diagnostic();
+        eprintln!("{}", diagnostic.render(&input, &input_file, color));
+    }
+    if !errors.is_empty() {
+        std::process::exit(1);
+    }
 
-fn line_col(input: &str, pos: usize) -> (usize, usize) {
-    let mut line = 1;
-    let mut col = 1;
-    for c in input.chars().take(pos) {
-        if c == '\n' {
-            line += 1;
-            col = 1;
-        } else {
-            col += 1;
-        }
-    }
-    (line, col)
+    for lint in yacc_parser::lint::lint(&grammar) {
+        let diagnostic = lint.to_diagnostic();
+        eprintln!("{}", diagnostic.render(&input, &input_file, color));
+    }
+
+    let errors = grammar.validate();
+    for error in &errors {
+        let diagnostic = error.to_diagnostic();
+        eprintln!("{}", diagnostic.render(&input, &input_file, color));
+    }
+    // The sets are still well-defined if e.g. a symbol is undefined
+    if dump_sets {
+        print!("{}", Analysis::new(&grammar));
+    }
+    if !errors.is_empty() {
+        std::process::exit(1);
+    }
+    // println!("{}", grammar);
 }
@@ -1,279 +1,623 @@
+use crate::error::ParseError;
 use crate::grammar::Alternative;
+use crate::grammar::DefineValue;
 use crate::grammar::Directive;
 use crate::grammar::Grammar;
+use crate::grammar::Item;
 use crate::grammar::Rule;
+use crate::grammar::Symbol;
+use crate::grammar::SymbolOrTag;
+use crate::grammar::Tag;
+use crate::grammar::TokenDecl;
 use crate::lexer::Lexer;
 use crate::token::Spanned;
 use crate::token::Token;
+use crate::token::TokenKind;
 
 pub struct Parser<'a> {
     input: &'a str,
-    lexer: std::iter::Peekable<Lexer<'a>>,
+    lexer: Lexer<'a>,
+    lookahead: Spanned<Token>,
+    // End of the last consumed token
+    prev_end: usize,
+    errors: Vec<ParseError>,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(input: &'a str, lexer: Lexer<'a>) -> Self {
+    pub fn new(input: &'a str, mut lexer: Lexer<'a>) -> Self {
+        let lookahead = lexer
+            .next()
+            .unwrap_or_else(|| Spanned::new(Token::Eof, input.len()..input.len()));
         Parser {
             input,
-            lexer: lexer.peekable(),
+            lexer,
+            lookahead,
+            prev_end: 0,
+            errors: Vec::new(),
         }
     }
 
     fn next(&mut self) -> Spanned<Token> {
-        self.debug("next");
-        self.lexer.next().unwrap()
+        let eof = self.input.len()..self.input.len();
+        let next = self
+            .lexer
+            .next()
+            .unwrap_or_else(|| Spanned::new(Token::Eof, eof));
+        let token = std::mem::replace(&mut self.lookahead, next);
+        self.prev_end = token.span.end;
+        token
     }
 
-    fn peek(&mut self) -> &Spanned<Token> {
-        self.debug("peek");
-        self.lexer.peek().unwrap()
+    fn peek(&self) -> &Spanned<Token> {
+        &self.lookahead
     }
 
     #[allow(dead_code)]
-    fn debug(&mut self, s: &str) {
-        let peek = self.lexer.peek().unwrap().clone();
-        let source = self.input[peek.span.clone().start..]
+    fn debug(&self, s: &str) {
+        let peek = self.peek();
+        let source = self.input[peek.span.start..]
             .chars()
             .take(200)
             .collect::<String>();
         println!(
             "Peek: [{s}] {:?} ({}) at {}",
             peek.data,
-            self.text(peek.clone()),
+            self.text(peek),
             source
         );
     }
 
-    fn text(&mut self, spanned: Spanned<Token>) -> &str {
+    fn text(&self, spanned: &Spanned<Token>) -> &'a str {
         &self.input[spanned.span.clone()]
     }
 
-    fn expect(&mut self, token: Token) -> Spanned<Token> {
-        let spanned = self.next();
-        if spanned.data != token {
-            panic!(
-                "Expected {:?}, found {:?} ({}) at byte {:?}",
-                token,
-                spanned.data,
-                self.text(spanned.clone()),
-                spanned.span.start
-            )
+    fn spanned_text(&self, spanned: &Spanned<Token>) -> Spanned<String> {
+        Spanned::new(self.text(spanned).to_string(), spanned.span.clone())
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<Spanned<Token>, ParseError> {
+        if self.peek().data.kind() != kind {
+            return Err(self.unexpected(&[kind]));
+        }
+        Ok(self.next())
+    }
+
+    // Builds the error for the lookahead appearing where one of `expected`
+    // should be. The lookahead is not consumed.
+    fn unexpected(&self, expected: &[TokenKind]) -> ParseError {
+        let found = self.peek().clone();
+        match found.data {
+            Token::Eof => ParseError::UnexpectedEof {
+                span: found.span,
+                expected: expected.to_vec(),
+            },
+            Token::UnterminatedCode => ParseError::UnterminatedCodeBlock { span: found.span },
+            _ => ParseError::UnexpectedToken {
+                found,
+                expected: expected.to_vec(),
+            },
+        }
+    }
+
+    // Records `error` and skips to the next directive or the end of the
+    // declarations section.
+    fn recover_directive(&mut self, error: ParseError) {
+        self.errors.push(error);
+        while !matches!(
+            self.peek().data,
+            Token::Directive | Token::Prologue | Token::PercentPercent | Token::Eof
+        ) {
+            self.next();
         }
-        spanned
     }
 
-    fn parse_directives(&mut self) -> Vec<Directive> {
+    // Records `error` and skips past the next `;`, or to the end of the rules
+    // section.
+    fn recover_rule(&mut self, error: ParseError) {
+        self.errors.push(error);
+        loop {
+            match self.peek().data {
+                Token::PercentPercent | Token::Eof => break,
+                Token::SemiColon => {
+                    self.next();
+                    break;
+                }
+                _ => {
+                    self.next();
+                }
+            }
+        }
+    }
+
+    fn parse_directives(&mut self) -> Vec<Spanned<Directive>> {
         let mut prelude = Vec::new();
         loop {
-            if let Token::Directive = self.peek().data {
-                prelude.push(self.parse_directive());
-            } else {
-                break;
+            match self.peek().data {
+                Token::PercentPercent | Token::Eof => break,
+                Token::Directive => match self.parse_directive() {
+                    Ok(directive) => prelude.push(directive),
+                    Err(error) => self.recover_directive(error),
+                },
+                Token::Prologue => {
+                    let prologue = self.next();
+                    // Strip the `%{` and `%}` delimiters
+                    let span = prologue.span.start + 2..prologue.span.end - 2;
+                    let code = Spanned::new(self.input[span.clone()].to_string(), span);
+                    prelude.push(Spanned::new(Directive::Prologue { code }, prologue.span));
+                }
+                _ => {
+                    let error = self.unexpected(&[
+                        TokenKind::Directive,
+                        TokenKind::Prologue,
+                        TokenKind::PercentPercent,
+                    ]);
+                    self.next();
+                    self.recover_directive(error);
+                }
             }
         }
         prelude
     }
 
-    fn parse_directive(&mut self) -> Directive {
-        let directive = self.expect(Token::Directive);
-        match &self.input[directive.span.clone()] {
+    fn parse_directive(&mut self) -> Result<Spanned<Directive>, ParseError> {
+        let directive = self.expect(TokenKind::Directive)?;
+        let start = directive.span.start;
+        let directive = match self.text(&directive) {
+            "%start" => {
+                let symbol = self.expect(TokenKind::Ident)?;
+                Directive::Start {
+                    symbol: self.spanned_text(&symbol),
+                }
+            }
+            "%define" => {
+                let name = self.expect(TokenKind::Ident)?;
+                let value = match self.peek().data {
+                    Token::Ident => {
+                        let value = self.next();
+                        let keyword = DefineValue::Keyword(self.text(&value).to_string());
+                        Some(Spanned::new(keyword, value.span))
+                    }
+                    Token::String | Token::Code => {
+                        let value = self.next();
+                        // Strip the quotes or braces
+                        let text = self.text(&value);
+                        let text = text[1..text.len() - 1].to_string();
+                        let value = match value.data {
+                            Token::String => Spanned::new(DefineValue::String(text), value.span),
+                            _ => Spanned::new(DefineValue::Code(text), value.span),
+                        };
+                        Some(value)
+                    }
+                    _ => None,
+                };
+                Directive::Define {
+                    name: self.spanned_text(&name),
+                    value,
+                }
+            }
+            "%code" => {
+                let qualifier = if self.peek().data == Token::Ident {
+                    let qualifier = self.next();
+                    Some(self.spanned_text(&qualifier))
+                } else {
+                    None
+                };
+                let code = self.expect(TokenKind::Code)?;
+                Directive::Code {
+                    qualifier,
+                    code: self.spanned_text(&code),
+                }
+            }
+            "%destructor" | "%printer" => {
+                let code = self.expect(TokenKind::Code)?;
+                let mut symbols = Vec::new();
+                loop {
+                    let symbol = match self.peek().data {
+                        Token::Ident | Token::Char(_) | Token::String => {
+                            let name = self.next();
+                            Spanned::new(
+                                SymbolOrTag::Symbol(self.text(&name).to_string()),
+                                name.span,
+                            )
+                        }
+                        Token::Type => {
+                            let tag = self.next();
+                            let tag = self.tag(&tag);
+                            Spanned::new(SymbolOrTag::Tag(tag.data), tag.span)
+                        }
+                        _ => break,
+                    };
+                    symbols.push(symbol);
+                }
+                let code = self.spanned_text(&code);
+                if self.text(&directive) == "%destructor" {
+                    Directive::Destructor { code, symbols }
+                } else {
+                    Directive::Printer { code, symbols }
+                }
+            }
+            "%initial-action" => {
+                let code = self.expect(TokenKind::Code)?;
+                Directive::InitialAction {
+                    code: self.spanned_text(&code),
+                }
+            }
             "%pure-parser" => Directive::PureParser,
             "%expect" => {
-                let number = self.expect(Token::Number);
+                let number = self.expect(TokenKind::Number)?;
+                let text = self.text(&number);
+                let value = text.parse().map_err(|_| ParseError::BadNumber {
+                    number: self.spanned_text(&number),
+                })?;
                 Directive::Expect {
-                    number: self.text(number).parse().unwrap(),
+                    number: Spanned::new(value, number.span),
                 }
             }
             "%name-prefix" => {
-                self.expect(Token::Equal);
-                let prefix = self.expect(Token::String);
+                self.expect(TokenKind::Equal)?;
+                let prefix = self.expect(TokenKind::String)?;
                 Directive::NamePrefix {
-                    prefix: self.text(prefix).to_string(),
+                    prefix: self.spanned_text(&prefix),
                 }
             }
             "%locations" => Directive::Locations,
             "%parse-param" => {
-                let params = self.expect(Token::Code);
+                let params = self.expect(TokenKind::Code)?;
                 Directive::ParseParam {
-                    params: self.input[params.span.clone()].to_string(),
+                    params: self.spanned_text(&params),
                 }
             }
             "%lex-param" => {
-                let program = self.expect(Token::Code);
+                let program = self.expect(TokenKind::Code)?;
                 Directive::LexProgram {
-                    params: self.input[program.span.clone()].to_string(),
+                    params: self.spanned_text(&program),
                 }
             }
             "%union" => {
-                let code = self.expect(Token::Code);
+                let code = self.expect(TokenKind::Code)?;
                 Directive::Union {
-                    code: self.input[code.span.clone()].to_string(),
+                    code: self.spanned_text(&code),
                 }
             }
             "%type" => {
-                let type_name = self.expect(Token::Type);
+                let type_name = self.expect(TokenKind::Type)?;
                 let mut rule_names = Vec::new();
-                loop {
-                    if !matches!(self.peek().data, Token::Ident) {
-                        break;
-                    }
-                    let rule_name = self.expect(Token::Ident);
-                    rule_names.push(self.input[rule_name.span.clone()].to_string());
+                while let Token::Ident = self.peek().data {
+                    let rule_name = self.expect(TokenKind::Ident)?;
+                    rule_names.push(self.spanned_text(&rule_name));
                 }
                 Directive::Type {
-                    type_name: self.input[type_name.span.clone()].to_string(),
+                    type_name: self.tag(&type_name),
                     rule_names,
                 }
             }
             "%token" => {
-                let token_name = if self.peek().data == Token::Type {
-                    let token_name = self.expect(Token::Type);
-                    Some(self.input[token_name.span.clone()].to_string())
-                } else {
-                    None
-                };
-                let mut rule_names = Vec::new();
+                // A tag applies to the names which follow it, up to the next tag
+                let mut tag = None;
+                let mut tokens = Vec::new();
                 loop {
-                    if let Some(ident) = self.rule_name() {
-                        rule_names.push(ident);
-                    } else {
-                        break;
+                    match self.peek().data {
+                        Token::Type => {
+                            let type_name = self.next();
+                            tag = Some(self.tag(&type_name));
+                        }
+                        Token::Ident | Token::Char(_) => {
+                            let name = self.next();
+                            let code = if self.peek().data == Token::Number {
+                                let number = self.next();
+                                let value = self.text(&number).parse().map_err(|_| {
+                                    ParseError::BadNumber {
+                                        number: self.spanned_text(&number),
+                                    }
+                                })?;
+                                Some(Spanned::new(value, number.span))
+                            } else {
+                                None
+                            };
+                            let alias = if self.peek().data == Token::String {
+                                let alias = self.next();
+                                Some(self.spanned_text(&alias))
+                            } else {
+                                None
+                            };
+                            tokens.push(TokenDecl {
+                                name: self.spanned_text(&name),
+                                code,
+                                alias,
+                                tag: tag.clone(),
+                            });
+                        }
+                        _ => break,
                     }
                 }
-                Directive::Token {
-                    token_name,
-                    rule_names,
-                }
+                Directive::Token { tokens }
             }
             "%left" => {
                 let mut rule_names = Vec::new();
-                loop {
-                    if let Some(ident) = self.rule_name() {
-                        rule_names.push(ident);
-                    } else {
-                        break;
-                    }
+                while let Some(ident) = self.rule_name() {
+                    rule_names.push(ident);
                 }
                 Directive::Left { rule_names }
             }
             "%right" => {
                 let mut rule_names = Vec::new();
-                loop {
-                    if let Some(ident) = self.rule_name() {
-                        rule_names.push(ident);
-                    } else {
-                        break;
-                    }
+                while let Some(ident) = self.rule_name() {
+                    rule_names.push(ident);
                 }
                 Directive::Right { rule_names }
             }
+            "%precedence" => {
+                let mut rule_names = Vec::new();
+                while let Some(ident) = self.rule_name() {
+                    rule_names.push(ident);
+                }
+                Directive::Precedence { rule_names }
+            }
+            "%nterm" => {
+                let type_name = if self.peek().data == Token::Type {
+                    let type_name = self.next();
+                    Some(self.tag(&type_name))
+                } else {
+                    None
+                };
+                let mut rule_names = Vec::new();
+                while let Token::Ident = self.peek().data {
+                    let rule_name = self.expect(TokenKind::Ident)?;
+                    rule_names.push(self.spanned_text(&rule_name));
+                }
+                Directive::Nterm {
+                    type_name,
+                    rule_names,
+                }
+            }
             "%nonassoc" => {
                 let mut rule_names = Vec::new();
-                loop {
-                    if let Some(ident) = self.rule_name() {
-                        rule_names.push(ident);
-                    } else {
-                        break;
-                    }
+                while let Some(ident) = self.rule_name() {
+                    rule_names.push(ident);
                 }
                 Directive::NonAssoc { rule_names }
             }
-            t => panic!("Unknown directive '{t}'"),
-        }
+            _ => {
+                return Err(ParseError::UnknownDirective {
+                    name: self.spanned_text(&directive),
+                })
+            }
+        };
+        Ok(Spanned::new(directive, start..self.prev_end))
     }
 
-    fn rule_name(&mut self) -> Option<String> {
+    fn tag(&self, spanned: &Spanned<Token>) -> Spanned<Tag> {
+        let text = self.text(spanned);
+        // Strip the angle brackets
+        let tag = match text[1..text.len() - 1].trim() {
+            "*" => Tag::Any,
+            "" => Tag::Untyped,
+            name => Tag::Named(name.to_string()),
+        };
+        Spanned::new(tag, spanned.span.clone())
+    }
+
+    fn rule_name(&mut self) -> Option<Spanned<String>> {
         match self.peek().data {
-            Token::Ident => {
-                let ident = self.expect(Token::Ident);
-                Some(self.input[ident.span.clone()].to_string())
-            }
-            Token::Char => {
-                let char = self.expect(Token::Char);
-                Some(self.input[char.span.clone()].to_string())
+            Token::Ident | Token::Char(_) | Token::String => {
+                let ident = self.next();
+                Some(self.spanned_text(&ident))
             }
             _ => None,
         }
     }
 
-    fn parse_rule(&mut self) -> Rule {
-        let name_token = self.expect(Token::Ident);
-        let name = self.input[name_token.span.clone()].to_string();
-        self.expect(Token::Colon);
+    fn parse_rule(&mut self) -> Option<Rule> {
+        let start = self.peek().span.start;
+        let (name, named_ref) = match self.parse_rule_name() {
+            Ok(name) => name,
+            Err(error) => {
+                self.recover_rule(error);
+                return None;
+            }
+        };
 
         let mut alternatives = Vec::new();
         loop {
-            let mut elements = Vec::new();
-            loop {
-                match self.peek().data {
-                    Token::Ident => {
-                        let element = self.expect(Token::Ident);
-                        elements.push(self.input[element.span.clone()].to_string());
-                    }
-                    Token::Char => {
-                        let char = self.expect(Token::Char);
-                        elements.push(self.input[char.span.clone()].to_string());
-                    }
-                    _ => break,
+            match self.parse_alternative() {
+                Ok(alternative) => alternatives.push(alternative),
+                Err(error) => {
+                    self.recover_rule(error);
+                    break;
                 }
             }
+            // Check if there are more alternatives
+            if self.next().data == Token::SemiColon {
+                break;
+            }
+        }
 
-            let action = if let Token::Code = self.peek().data {
-                let code = self.expect(Token::Code);
-                Some(self.input[code.span.clone()].to_string())
-            } else {
-                None
+        Some(Rule {
+            name,
+            named_ref,
+            alternatives,
+            span: start..self.prev_end,
+        })
+    }
+
+    // Parse `name:` or `name[ref]:`
+    fn parse_rule_name(
+        &mut self,
+    ) -> Result<(Spanned<String>, Option<Spanned<String>>), ParseError> {
+        let name_token = self.expect(TokenKind::Ident)?;
+        let named_ref = self.parse_named_ref();
+        if self.peek().data != Token::Colon {
+            let expected: &[TokenKind] = match named_ref {
+                Some(_) => &[TokenKind::Colon],
+                None => &[TokenKind::NamedRef, TokenKind::Colon],
             };
+            return Err(self.unexpected(expected));
+        }
+        self.next();
+        Ok((self.spanned_text(&name_token), named_ref))
+    }
 
-            alternatives.push(Alternative { elements, action });
+    // Parse `[name]` if it comes next
+    fn parse_named_ref(&mut self) -> Option<Spanned<String>> {
+        if self.peek().data != Token::NamedRef {
+            return None;
+        }
+        let token = self.next();
+        let text = self.text(&token);
+        Some(Spanned::new(
+            text[1..text.len() - 1].to_string(),
+            token.span.clone(),
+        ))
+    }
 
-            // Check if there are more alternatives
+    // Parse the elements and action of an alternative, up to but not
+    // including the following '|' or ';'
+    fn parse_alternative(&mut self) -> Result<Alternative, ParseError> {
+        let start = self.peek().span.start;
+        let mut items = Vec::new();
+        let mut precedence = None;
+        let mut empty = None;
+        loop {
             match self.peek().data {
-                Token::Bar => {
-                    self.expect(Token::Bar);
+                // A string literal refers to a token by its alias
+                Token::Ident | Token::Char(_) | Token::String => {
+                    let symbol = self.next();
+                    items.push(Item::Symbol {
+                        symbol: self.spanned_text(&symbol),
+                        named_ref: self.parse_named_ref(),
+                    });
                 }
-                Token::SemiColon => {
-                    self.expect(Token::SemiColon);
-                    break;
+                Token::Code => {
+                    let code = self.next();
+                    items.push(Item::Action(self.spanned_text(&code)));
+                }
+                // %prec may appear anywhere, but only once
+                Token::Directive if precedence.is_none() && self.text(self.peek()) == "%prec" => {
+                    precedence = self.parse_precedence()?;
                 }
-                _ => panic!("Expected '|' or ';', found {:?}", self.peek().data),
+                Token::Directive if empty.is_none() && self.text(self.peek()) == "%empty" => {
+                    empty = Some(self.next().span);
+                }
+                _ => break,
             }
         }
 
-        Rule { name, alternatives }
+        // The last action is the action of the alternative, the others are
+        // mid-rule actions
+        let action = match items.pop() {
+            Some(Item::Action(action)) => Some(action),
+            item => {
+                items.extend(item);
+                None
+            }
+        };
+
+        // An empty alternative is located where it would start
+        let end = self.prev_end.max(start);
+        match self.peek().data {
+            Token::Bar | Token::SemiColon => Ok(Alternative {
+                items,
+                empty,
+                precedence,
+                action,
+                span: start..end,
+            }),
+            // Only %prec and %empty may appear inside an alternative
+            Token::Directive if !matches!(self.text(self.peek()), "%prec" | "%empty") => {
+                let directive = self.next();
+                Err(ParseError::UnknownDirective {
+                    name: self.spanned_text(&directive),
+                })
+            }
+            _ => Err(self.unexpected(&[
+                TokenKind::Ident,
+                TokenKind::Char,
+                TokenKind::String,
+                TokenKind::Code,
+                TokenKind::Bar,
+                TokenKind::SemiColon,
+            ])),
+        }
+    }
+
+    // Parse `%prec symbol` if it comes next
+    fn parse_precedence(&mut self) -> Result<Option<Symbol>, ParseError> {
+        if self.peek().data != Token::Directive || self.text(self.peek()) != "%prec" {
+            return Ok(None);
+        }
+        self.next();
+        match self.rule_name() {
+            Some(symbol) => Ok(Some(symbol)),
+            None => Err(self.unexpected(&[TokenKind::Ident, TokenKind::Char, TokenKind::String])),
+        }
     }
 
     // Parse all rules
     fn parse_rules(&mut self) -> Vec<Rule> {
         let mut rules = Vec::new();
-        while let Token::Ident = self.peek().data {
-            rules.push(self.parse_rule());
+        loop {
+            match self.peek().data {
+                Token::PercentPercent | Token::Eof => break,
+                Token::Ident => rules.extend(self.parse_rule()),
+                _ => {
+                    let error = self.unexpected(&[
+                        TokenKind::Ident,
+                        TokenKind::PercentPercent,
+                        TokenKind::Eof,
+                    ]);
+                    self.recover_rule(error);
+                }
+            }
         }
         rules
     }
 
-    // Parse the optional programs/code section
-    #[allow(dead_code)]
-    fn parse_programs(&mut self) -> String {
-        if let Token::Code = self.peek().data {
-            let code = self.expect(Token::Code);
-            self.input[code.span.clone()].to_string()
+    // Parse the optional programs section after the second '%%'
+    fn parse_programs(&mut self) -> Result<Option<Spanned<String>>, ParseError> {
+        if self.peek().data != Token::PercentPercent {
+            return Ok(None);
+        }
+        self.next();
+        let programs = self.expect(TokenKind::Epilogue)?;
+        Ok(Some(self.spanned_text(&programs)))
+    }
+
+    /// Parses the grammar, returning the first error if there is any.
+    pub fn parse_grammar(&mut self) -> Result<Grammar, ParseError> {
+        let (grammar, mut errors) = self.parse_grammar_with_recovery();
+        if errors.is_empty() {
+            Ok(grammar)
         } else {
-            String::new()
+            Err(errors.swap_remove(0))
         }
     }
 
-    pub fn parse_grammar(&mut self) -> Grammar {
+    /// Parses the grammar, recovering from errors by skipping to the next
+    /// directive or rule. Returns the partial grammar together with every
+    /// error which was encountered.
+    pub fn parse_grammar_with_recovery(&mut self) -> (Grammar, Vec<ParseError>) {
         let declarations = self.parse_directives();
-        self.expect(Token::PercentPercent);
-        let rules = self.parse_rules();
-        // let programs = self.parse_programs();
-        // self.expect(Token::Eof);
+        let rules = match self.expect(TokenKind::PercentPercent) {
+            Ok(_) => self.parse_rules(),
+            Err(error) => {
+                self.errors.push(error);
+                Vec::new()
+            }
+        };
+        let programs = match self.parse_programs() {
+            Ok(programs) => programs,
+            Err(error) => {
+                self.errors.push(error);
+                None
+            }
+        };
 
-        Grammar {
+        let grammar = Grammar {
             directives: declarations,
             rules,
-            programs: String::new(),
-        }
+            programs,
+            span: 0..self.input.len(),
+        };
+        (grammar, std::mem::take(&mut self.errors))
     }
 }
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use crate::grammar::Associativity;
+use crate::grammar::Directive;
+use crate::grammar::Grammar;
+use crate::grammar::Item;
+use crate::grammar::Tag;
+use crate::token::Span;
+use crate::token::Spanned;
+
+// An index into a `SymbolTable`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SymbolId(usize);
+
+impl SymbolId {
+    // $end, the token which marks the end of the input
+    pub const END: SymbolId = SymbolId(0);
+
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    // Declared by `%token` or a precedence directive, a character literal
+    // or a string literal
+    Terminal,
+    // Has rules, or is declared by `%nterm`
+    Nonterminal,
+    // Used in a rule, but neither declared nor defined
+    Undefined,
+}
+
+#[derive(Debug, Clone)]
+pub struct SymbolInfo {
+    // The name as first written, e.g. `expr`, `'+'` or `"<="`
+    pub name: String,
+    pub kind: SymbolKind,
+    // The string alias of a token, including the quotes
+    pub alias: Option<String>,
+    pub tag: Option<Tag>,
+    pub precedence: Option<(usize, Associativity)>,
+    // Where the symbol is declared by a directive, or otherwise where its
+    // first rule is
+    pub declaration: Option<Span>,
+    // Every occurrence on a right-hand side or after `%prec`
+    pub uses: Vec<Span>,
+}
+
+// Every symbol of a grammar, interned into a `SymbolId`, starting with the
+// implicit `$end`. A token and its string alias share an id, as do character
+// literals written differently, e.g. `'A'` and `'\x41'`.
+#[derive(Debug, Clone)]
+pub struct SymbolTable {
+    symbols: Vec<SymbolInfo>,
+    ids: HashMap<String, SymbolId>,
+    chars: HashMap<char, SymbolId>,
+}
+
+impl SymbolTable {
+    pub fn new(grammar: &Grammar) -> Self {
+        let mut table = SymbolTable {
+            symbols: Vec::new(),
+            ids: HashMap::new(),
+            chars: HashMap::new(),
+        };
+        table.intern("$end");
+
+        let mut level = 0;
+        for directive in &grammar.directives {
+            match &directive.data {
+                Directive::Token { tokens } => {
+                    for token in tokens {
+                        let id = table.declare(&token.name, SymbolKind::Terminal);
+                        if let Some(alias) = &token.alias {
+                            table.ids.insert(alias.data.clone(), id);
+                            table.symbols[id.0].alias = Some(alias.data.clone());
+                        }
+                        if let Some(tag) = &token.tag {
+                            table.symbols[id.0].tag = Some(tag.data.clone());
+                        }
+                    }
+                }
+                Directive::Left { rule_names }
+                | Directive::Right { rule_names }
+                | Directive::NonAssoc { rule_names }
+                | Directive::Precedence { rule_names } => {
+                    level += 1;
+                    let associativity = match &directive.data {
+                        Directive::Left { .. } => Associativity::Left,
+                        Directive::Right { .. } => Associativity::Right,
+                        Directive::NonAssoc { .. } => Associativity::NonAssoc,
+                        _ => Associativity::None,
+                    };
+                    for name in rule_names {
+                        let id = table.declare(name, SymbolKind::Terminal);
+                        table.symbols[id.0].precedence = Some((level, associativity));
+                    }
+                }
+                Directive::Type {
+                    type_name,
+                    rule_names,
+                } => {
+                    for name in rule_names {
+                        let id = table.intern(&name.data);
+                        table.symbols[id.0].tag = Some(type_name.data.clone());
+                    }
+                }
+                Directive::Nterm {
+                    type_name,
+                    rule_names,
+                } => {
+                    for name in rule_names {
+                        let id = table.declare(name, SymbolKind::Nonterminal);
+                        if let Some(type_name) = type_name {
+                            table.symbols[id.0].tag = Some(type_name.data.clone());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Rules make their left-hand side a nonterminal, even if it was
+        // declared as a token
+        for rule in &grammar.rules {
+            let id = table.declare(&rule.name, SymbolKind::Nonterminal);
+            table.symbols[id.0].kind = SymbolKind::Nonterminal;
+        }
+
+        for alternative in grammar.rules.iter().flat_map(|rule| &rule.alternatives) {
+            for item in &alternative.items {
+                if let Item::Symbol { symbol, .. } = item {
+                    table.use_symbol(symbol);
+                }
+            }
+            if let Some(precedence) = &alternative.precedence {
+                table.use_symbol(precedence);
+            }
+        }
+        table
+    }
+
+    /// The id of the symbol called `name`, which may also be the string alias
+    /// of a token or a character literal.
+    pub fn id(&self, name: &str) -> Option<SymbolId> {
+        match char_literal(name) {
+            Some(c) => self.chars.get(&c).copied(),
+            None => self.ids.get(name).copied(),
+        }
+    }
+
+    pub fn get(&self, id: SymbolId) -> &SymbolInfo {
+        &self.symbols[id.0]
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Every symbol, in order of first appearance in the grammar.
+    pub fn iter(&self) -> impl Iterator<Item = (SymbolId, &SymbolInfo)> {
+        self.symbols
+            .iter()
+            .enumerate()
+            .map(|(i, symbol)| (SymbolId(i), symbol))
+    }
+
+    pub fn terminals(&self) -> impl Iterator<Item = (SymbolId, &SymbolInfo)> {
+        self.of_kind(SymbolKind::Terminal)
+    }
+
+    pub fn nonterminals(&self) -> impl Iterator<Item = (SymbolId, &SymbolInfo)> {
+        self.of_kind(SymbolKind::Nonterminal)
+    }
+
+    /// The symbols which are used in rules, but never declared or defined.
+    pub fn undefined(&self) -> impl Iterator<Item = (SymbolId, &SymbolInfo)> {
+        self.of_kind(SymbolKind::Undefined)
+    }
+
+    fn of_kind(&self, kind: SymbolKind) -> impl Iterator<Item = (SymbolId, &SymbolInfo)> {
+        self.iter().filter(move |(_, symbol)| symbol.kind == kind)
+    }
+
+    // Returns the id of `name`, adding it as an undefined symbol if it is new
+    fn intern(&mut self, name: &str) -> SymbolId {
+        if let Some(id) = self.id(name) {
+            return id;
+        }
+        let id = SymbolId(self.symbols.len());
+        // Literals are tokens by definition
+        let kind = if name.starts_with(['\'', '"']) || name == "error" || name == "$end" {
+            SymbolKind::Terminal
+        } else {
+            SymbolKind::Undefined
+        };
+        self.symbols.push(SymbolInfo {
+            name: name.to_string(),
+            kind,
+            alias: None,
+            tag: None,
+            precedence: None,
+            declaration: None,
+            uses: Vec::new(),
+        });
+        match char_literal(name) {
+            Some(c) => self.chars.insert(c, id),
+            None => self.ids.insert(name.to_string(), id),
+        };
+        id
+    }
+
+    // Records a declaration of `name` as a symbol of the given kind, unless
+    // it is already declared
+    fn declare(&mut self, name: &Spanned<String>, kind: SymbolKind) -> SymbolId {
+        let id = self.intern(&name.data);
+        let symbol = &mut self.symbols[id.0];
+        if symbol.kind == SymbolKind::Undefined {
+            symbol.kind = kind;
+        }
+        if symbol.declaration.is_none() {
+            symbol.declaration = Some(name.span.clone());
+        }
+        id
+    }
+
+    fn use_symbol(&mut self, name: &Spanned<String>) {
+        let id = self.intern(&name.data);
+        self.symbols[id.0].uses.push(name.span.clone());
+    }
+}
+
+// The value of a character literal such as `'a'` or `'\n'`
+fn char_literal(name: &str) -> Option<char> {
+    let (c, len) = crate::lexer::unescape(name.strip_prefix('\'')?)?;
+    (&name[len + 1..] == "'").then_some(c)
+}
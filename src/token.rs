@@ -8,24 +8,84 @@ pub enum Token {
     Colon,          // :
     SemiColon,      // ;
     Code,           // { ... }
-    Ident,          // [a-zA-Z_][a-zA-Z0-9_]*|'.'
+    Prologue,       // %{ ... %}
+    Epilogue,       // Everything after the second %%
+    Ident,          // [a-zA-Z_][a-zA-Z0-9_.-]*
     String,         // '...'
     Eof,            // End of file
     Directive,      // %ident ...
-    Char,           // 'a'
+    Char(char),     // 'a', with escape sequences decoded
     Number,         // 123
     Type,
+    NamedRef,         // [name]
+    UnterminatedCode, // { ... or %{ ... without its end
     Err,
 }
 
-#[derive(Debug, Clone)]
+// A token without its payload, e.g. in the set of tokens which a parse
+// error expected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Equal,
+    PercentPercent,
+    GreaterThan,
+    LessThan,
+    Bar,
+    Colon,
+    SemiColon,
+    Code,
+    Prologue,
+    Epilogue,
+    Ident,
+    String,
+    Eof,
+    Directive,
+    Char,
+    Number,
+    Type,
+    NamedRef,
+    UnterminatedCode,
+    Err,
+}
+
+impl Token {
+    pub fn kind(&self) -> TokenKind {
+        match self {
+            Token::Equal => TokenKind::Equal,
+            Token::PercentPercent => TokenKind::PercentPercent,
+            Token::GreaterThan => TokenKind::GreaterThan,
+            Token::LessThan => TokenKind::LessThan,
+            Token::Bar => TokenKind::Bar,
+            Token::Colon => TokenKind::Colon,
+            Token::SemiColon => TokenKind::SemiColon,
+            Token::Code => TokenKind::Code,
+            Token::Prologue => TokenKind::Prologue,
+            Token::Epilogue => TokenKind::Epilogue,
+            Token::Ident => TokenKind::Ident,
+            Token::String => TokenKind::String,
+            Token::Eof => TokenKind::Eof,
+            Token::Directive => TokenKind::Directive,
+            Token::Char(_) => TokenKind::Char,
+            Token::Number => TokenKind::Number,
+            Token::Type => TokenKind::Type,
+            Token::NamedRef => TokenKind::NamedRef,
+            Token::UnterminatedCode => TokenKind::UnterminatedCode,
+            Token::Err => TokenKind::Err,
+        }
+    }
+}
+
+// Byte range into the input.
+pub type Span = std::ops::Range<usize>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Spanned<T> {
     pub data: T,
-    pub span: std::ops::Range<usize>,
+    pub span: Span,
 }
 
 impl<T> Spanned<T> {
-    pub fn new(data: T, span: std::ops::Range<usize>) -> Self {
+    pub fn new(data: T, span: Span) -> Self {
         Spanned { data, span }
     }
 }
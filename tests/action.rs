@@ -0,0 +1,99 @@
+use yacc_parser::action::Reference;
+use yacc_parser::action::ReferenceKind;
+use yacc_parser::action::Target;
+use yacc_parser::grammar::Tag;
+use yacc_parser::token::Spanned;
+
+// Where the action starts in the (imaginary) input
+const OFFSET: usize = 100;
+
+fn references(code: &str) -> Vec<Reference> {
+    let code = Spanned::new(code.to_string(), OFFSET..OFFSET + code.len());
+    yacc_parser::action::references(&code)
+}
+
+// The references in `code` as `(text, kind, target, tag)`, where `text` is
+// sliced from `code` using the span
+fn summary(code: &str) -> Vec<(&str, ReferenceKind, Target, Option<Tag>)> {
+    references(code)
+        .into_iter()
+        .map(|r| {
+            let text = &code[r.span.start - OFFSET..r.span.end - OFFSET];
+            (text, r.kind, r.target, r.tag)
+        })
+        .collect()
+}
+
+fn named(name: &str) -> Option<Tag> {
+    Some(Tag::Named(name.to_string()))
+}
+
+#[test]
+fn finds_value_and_location_references() {
+    use ReferenceKind::Location;
+    use ReferenceKind::Value;
+    assert_eq!(
+        summary("{ $$ = $1 + $-1 + $<std::vector<int>>$ + @$ + @3 + $name + $[a.b]; }"),
+        [
+            ("$$", Value, Target::Lhs, None),
+            ("$1", Value, Target::Index(1), None),
+            ("$-1", Value, Target::Index(-1), None),
+            (
+                "$<std::vector<int>>$",
+                Value,
+                Target::Lhs,
+                named("std::vector<int>")
+            ),
+            ("@$", Location, Target::Lhs, None),
+            ("@3", Location, Target::Index(3), None),
+            ("$name", Value, Target::Name("name".to_string()), None),
+            ("$[a.b]", Value, Target::Name("a.b".to_string()), None),
+        ]
+    );
+}
+
+#[test]
+fn parses_tags() {
+    let tags: Vec<_> = references("{ $<ival>1 $< ival >2 $<>3 $<*>4 $<a->b>5 }")
+        .into_iter()
+        .map(|r| r.tag)
+        .collect();
+    assert_eq!(
+        tags,
+        [
+            named("ival"),
+            named("ival"),
+            Some(Tag::Untyped),
+            Some(Tag::Any),
+            named("a->b"),
+        ]
+    );
+}
+
+#[test]
+fn skips_strings_chars_and_comments() {
+    let code = r#"{ printf("$1 \" $2"); c = '$'; d = '\''; /* $3 */ // $4
+                    $5; }"#;
+    let texts: Vec<_> = summary(code).into_iter().map(|r| r.0).collect();
+    assert_eq!(texts, ["$5"]);
+}
+
+#[test]
+fn skips_things_which_are_not_references() {
+    let texts: Vec<_> = summary("{ $ + $- + @ + @<t>1 + $[x + $1; }")
+        .into_iter()
+        .map(|r| r.0)
+        .collect();
+    assert_eq!(texts, ["$1"]);
+}
+
+#[test]
+fn ends_an_unterminated_tag_at_the_end_of_the_statement() {
+    let texts: Vec<_> = summary("{ $<int x = 1; y = a > $2; }")
+        .into_iter()
+        .map(|r| r.0)
+        .collect();
+    assert_eq!(texts, ["$2"]);
+    let texts: Vec<_> = summary("{ $<int").into_iter().map(|r| r.0).collect();
+    assert!(texts.is_empty());
+}
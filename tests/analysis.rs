@@ -0,0 +1,155 @@
+use std::collections::BTreeSet;
+
+use yacc_parser::analysis::Analysis;
+use yacc_parser::symbols::SymbolId;
+
+// The expression grammar of the dragon book, without left recursion
+const EXPRESSIONS: &str = "\
+%token ID
+%%
+e: t ep;
+ep: '+' t ep | %empty;
+t: f tp;
+tp: '*' f tp | %empty;
+f: '(' e ')' | ID;
+";
+
+// Every nonterminal is nullable, through a chain of nullable rules
+const CHAIN: &str = "\
+%token x z
+%%
+s: a b c;
+a: %empty | x;
+b: a a;
+c: b | z;
+";
+
+fn analyze(input: &str) -> Analysis {
+    Analysis::new(&yacc_parser::parse(input).unwrap())
+}
+
+fn id(analysis: &Analysis, name: &str) -> SymbolId {
+    analysis.symbols().id(name).unwrap()
+}
+
+fn names<'a>(analysis: &'a Analysis, set: &BTreeSet<SymbolId>) -> BTreeSet<&'a str> {
+    set.iter()
+        .map(|&symbol| analysis.symbols().get(symbol).name.as_str())
+        .collect()
+}
+
+fn first<'a>(analysis: &'a Analysis, name: &str) -> BTreeSet<&'a str> {
+    names(analysis, analysis.first(id(analysis, name)))
+}
+
+fn follow<'a>(analysis: &'a Analysis, name: &str) -> BTreeSet<&'a str> {
+    names(analysis, analysis.follow(id(analysis, name)))
+}
+
+fn set<'a>(names: &[&'a str]) -> BTreeSet<&'a str> {
+    names.iter().copied().collect()
+}
+
+#[test]
+fn nullable() {
+    let analysis = analyze(EXPRESSIONS);
+    for (name, nullable) in [
+        ("e", false),
+        ("ep", true),
+        ("t", false),
+        ("tp", true),
+        ("f", false),
+        ("ID", false),
+        ("'+'", false),
+    ] {
+        assert_eq!(
+            analysis.is_nullable(id(&analysis, name)),
+            nullable,
+            "{}",
+            name
+        );
+    }
+
+    let analysis = analyze(CHAIN);
+    for name in ["s", "a", "b", "c"] {
+        assert!(analysis.is_nullable(id(&analysis, name)), "{}", name);
+    }
+}
+
+#[test]
+fn first_sets() {
+    let analysis = analyze(EXPRESSIONS);
+    for name in ["e", "t", "f"] {
+        assert_eq!(first(&analysis, name), set(&["'('", "ID"]), "{}", name);
+    }
+    assert_eq!(first(&analysis, "ep"), set(&["'+'"]));
+    assert_eq!(first(&analysis, "tp"), set(&["'*'"]));
+    // A terminal begins with itself
+    assert_eq!(first(&analysis, "ID"), set(&["ID"]));
+
+    let analysis = analyze(CHAIN);
+    assert_eq!(first(&analysis, "a"), set(&["x"]));
+    assert_eq!(first(&analysis, "b"), set(&["x"]));
+    assert_eq!(first(&analysis, "c"), set(&["x", "z"]));
+    assert_eq!(first(&analysis, "s"), set(&["x", "z"]));
+}
+
+#[test]
+fn first_of_strings() {
+    let analysis = analyze(EXPRESSIONS);
+    let string = |names: &[&str]| -> Vec<SymbolId> {
+        names.iter().map(|name| id(&analysis, name)).collect()
+    };
+
+    let nullable = string(&["tp", "ep"]);
+    assert_eq!(
+        names(&analysis, &analysis.first_of_string(&nullable)),
+        set(&["'*'", "'+'"])
+    );
+    assert!(analysis.is_nullable_string(&nullable));
+
+    let stops = string(&["tp", "')'", "ep"]);
+    assert_eq!(
+        names(&analysis, &analysis.first_of_string(&stops)),
+        set(&["'*'", "')'"])
+    );
+    assert!(!analysis.is_nullable_string(&stops));
+
+    assert!(analysis.first_of_string(&[]).is_empty());
+    assert!(analysis.is_nullable_string(&[]));
+}
+
+#[test]
+fn follow_sets() {
+    let analysis = analyze(EXPRESSIONS);
+    for name in ["e", "ep"] {
+        assert_eq!(follow(&analysis, name), set(&["$end", "')'"]), "{}", name);
+    }
+    for name in ["t", "tp"] {
+        assert_eq!(
+            follow(&analysis, name),
+            set(&["$end", "'+'", "')'"]),
+            "{}",
+            name
+        );
+    }
+    assert_eq!(follow(&analysis, "f"), set(&["$end", "'+'", "'*'", "')'"]));
+}
+
+#[test]
+fn follow_passes_through_nullable_suffixes() {
+    let analysis = analyze(CHAIN);
+    // `s` is the start symbol, and `c` ends it
+    assert_eq!(follow(&analysis, "s"), set(&["$end"]));
+    assert_eq!(follow(&analysis, "c"), set(&["$end"]));
+    // `b c` is nullable, so `a` is also followed by whatever follows `s`
+    assert_eq!(follow(&analysis, "a"), set(&["$end", "x", "z"]));
+    assert_eq!(follow(&analysis, "b"), set(&["$end", "x", "z"]));
+}
+
+#[test]
+fn follow_of_the_declared_start_symbol() {
+    let analysis = analyze("%token x\n%start b\n%%\na: b x;\nb: x;\n");
+    assert_eq!(follow(&analysis, "b"), set(&["$end", "x"]));
+    assert!(follow(&analysis, "a").is_empty());
+}
@@ -0,0 +1,44 @@
+use yacc_parser::grammar::Grammar;
+use yacc_parser::grammar::Item;
+
+fn desugar(input: &str) -> Grammar {
+    yacc_parser::parse(input)
+        .unwrap()
+        .desugar_mid_rule_actions()
+}
+
+#[test]
+fn keeps_the_start_symbol() {
+    let grammar = desugar("%token A B\n%%\na: A[l] { $$ = $l; } B { $$ = $2; };\n");
+    assert_eq!(grammar.start_symbol().unwrap().data, "a");
+    assert!(grammar.validate().is_empty());
+    assert!(yacc_parser::lint::lint(&grammar).is_empty());
+}
+
+#[test]
+fn names_actions_by_whether_their_value_is_used() {
+    let grammar = desugar("%token A\n%%\na: { x; } A { $$ = 1; } A { y; } A { $$ = $5; };\n");
+    let names: Vec<_> = grammar.rules.iter().map(|rule| &rule.name.data).collect();
+    assert_eq!(names, ["$@1", "@2", "@3", "a"]);
+    let a = grammar.rule("a").unwrap();
+    assert!(a.alternatives[0]
+        .items
+        .iter()
+        .all(|item| matches!(item, Item::Symbol { .. })));
+    for rule in &grammar.rules[..3] {
+        assert!(rule.alternatives[0].empty.is_some());
+    }
+}
+
+#[test]
+fn rewrites_references_relative_to_the_stack() {
+    let grammar = desugar(
+        "%token A\n%token <ival> C\n%%\n\
+         a: C[c] A { $$ = $1 + @2 + $0 + $c + $<t>2 + $$; } A;\n",
+    );
+    let action = grammar.rules[0].alternatives[0].action.as_ref().unwrap();
+    assert_eq!(
+        action.data,
+        "{ $$ = $<ival>-1 + @0 + $-2 + $<ival>-1 + $<t>0 + $$; }"
+    );
+}
@@ -0,0 +1,67 @@
+use yacc_parser::lexer::Lexer;
+use yacc_parser::token::Spanned;
+use yacc_parser::token::Token;
+
+fn tokens(input: &str) -> Vec<Spanned<Token>> {
+    Lexer::new(input).collect()
+}
+
+#[test]
+fn braces_in_literals_and_comments_do_not_end_a_block() {
+    for block in [
+        r#"{ printf("}"); }"#,
+        r#"{ printf("\"}"); }"#,
+        "{ c = '}'; }",
+        "{ c = '\\''; }",
+        "{ /* } */ x; }",
+        "{ // }\n x; }",
+        "{ if (x) { y; } }",
+    ] {
+        assert_eq!(
+            tokens(block),
+            [
+                Spanned::new(Token::Code, 0..block.len()),
+                Spanned::new(Token::Eof, block.len()..block.len()),
+            ],
+            "{:?}",
+            block
+        );
+    }
+}
+
+#[test]
+fn an_unterminated_string_ends_at_the_end_of_its_line() {
+    // The stray quote does not swallow the rest of the block
+    let block = "{ x = $y's;\n }";
+    assert_eq!(tokens(block)[0], Spanned::new(Token::Code, 0..block.len()));
+}
+
+#[test]
+fn reports_unterminated_blocks() {
+    for input in [
+        "{ x;",
+        "{ /* } ",
+        "{ s = \"}\n",
+        "{ c = '}\n",
+        "{ { x; }",
+        "%{ #include <stdio.h>",
+    ] {
+        assert_eq!(
+            tokens(input),
+            [
+                Spanned::new(Token::UnterminatedCode, 0..input.len()),
+                Spanned::new(Token::Eof, input.len()..input.len()),
+            ],
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn unterminated_blocks_become_parse_errors() {
+    let input = "%%\na: b { x; ";
+    let error = yacc_parser::parse(input).unwrap_err();
+    assert_eq!(error.to_string(), "unterminated code block");
+    assert_eq!(error.line_col(input), (2, 6));
+}
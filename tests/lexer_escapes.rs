@@ -0,0 +1,69 @@
+use yacc_parser::lexer::Lexer;
+use yacc_parser::token::Spanned;
+use yacc_parser::token::Token;
+
+fn first_token(input: &str) -> Spanned<Token> {
+    Lexer::new(input).next().unwrap()
+}
+
+#[test]
+fn decodes_character_literals() {
+    for (literal, value) in [
+        ("'a'", 'a'),
+        ("'é'", 'é'),
+        ("'中'", '中'),
+        ("'\\n'", '\n'),
+        ("'\\t'", '\t'),
+        ("'\\a'", '\x07'),
+        ("'\\v'", '\x0b'),
+        ("'\\''", '\''),
+        ("'\\\\'", '\\'),
+        ("'\\\"'", '"'),
+        ("'\\?'", '?'),
+        ("'\"'", '"'),
+        // Octal, with one to three digits
+        ("'\\0'", '\0'),
+        ("'\\7'", '\x07'),
+        ("'\\101'", 'A'),
+        ("'\\377'", 'ÿ'),
+        // Hexadecimal, with any number of digits
+        ("'\\x41'", 'A'),
+        ("'\\x7f'", '\x7f'),
+        ("'\\x000041'", 'A'),
+        ("'\\u00e9'", 'é'),
+        ("'\\U0001F600'", '😀'),
+    ] {
+        let token = first_token(literal);
+        assert_eq!(token.data, Token::Char(value), "{}", literal);
+        assert_eq!(token.span, 0..literal.len(), "{}", literal);
+    }
+}
+
+#[test]
+fn rejects_bad_character_literals() {
+    for literal in [
+        "''",
+        "'ab'",
+        "'a",
+        "'\\q'",
+        "'\\8'",
+        "'\\1011'",
+        "'\\x'",
+        "'\\xg'",
+        "'\\x110000'",
+        "'\\u12'",
+        "'\\u12345'",
+        "'\\uD800'",
+        "'\\U0001F60'",
+        "'\\U00110000'",
+        "'\n'",
+    ] {
+        assert_eq!(first_token(literal).data, Token::Err, "{:?}", literal);
+    }
+}
+
+#[test]
+fn resumes_after_a_bad_literal() {
+    let tokens: Vec<_> = Lexer::new("'\\q' b 'x").map(|token| token.data).collect();
+    assert_eq!(tokens, [Token::Err, Token::Ident, Token::Err, Token::Eof]);
+}
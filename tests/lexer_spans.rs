@@ -0,0 +1,121 @@
+use yacc_parser::lexer::Lexer;
+use yacc_parser::token::Token;
+
+// Fragments of grammar syntax, so that documents exercise every branch of the
+// lexer rather than mostly producing `Token::Err`.
+const FRAGMENTS: &[&str] = &[
+    "%%",
+    "%{",
+    "%}",
+    "%token",
+    "%type",
+    "%left",
+    "%",
+    "{",
+    "}",
+    "<",
+    ">",
+    "<*>",
+    "->",
+    "'",
+    "'\\n'",
+    "'\\x41'",
+    "'é'",
+    "\\",
+    "\"",
+    "\"ü\"",
+    "/*",
+    "*/",
+    "//",
+    "\n",
+    " ",
+    "\t",
+    ":",
+    ";",
+    "|",
+    "=",
+    "42",
+    "expr",
+    "api.value.type",
+    "_",
+    "$$",
+    "$1",
+    "@2",
+    "[left]",
+    "[",
+];
+
+// Characters of various UTF-8 encoded lengths.
+const CHARS: &[char] = &[
+    'a',
+    'é',
+    'ß',
+    '€',
+    '中',
+    '😀',
+    '\u{10FFFF}',
+    '\u{301}',
+    '\r',
+];
+
+// A small xorshift generator, so that failures are reproducible from the seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn document(rng: &mut Rng) -> String {
+    let mut doc = String::new();
+    for _ in 0..rng.below(64) {
+        match rng.below(4) {
+            0 => doc.push(CHARS[rng.below(CHARS.len())]),
+            1 => doc.push(char::from_u32(rng.below(0x11000) as u32).unwrap_or('?')),
+            _ => doc.push_str(FRAGMENTS[rng.below(FRAGMENTS.len())]),
+        }
+    }
+    doc
+}
+
+#[test]
+fn spans_slice_random_utf8_documents() {
+    let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+    for _ in 0..20_000 {
+        let doc = document(&mut rng);
+        let tokens: Vec<_> = Lexer::new(&doc).collect();
+        let mut end = 0;
+        for token in &tokens {
+            let span = token.span.clone();
+            assert!(
+                doc.get(span.clone()).is_some(),
+                "{:?} does not slice {:?}",
+                token,
+                doc
+            );
+            assert!(span.start >= end, "{:?} overlaps in {:?}", token, doc);
+            end = span.end;
+        }
+        assert_eq!(
+            tokens.last().map(|t| &t.data),
+            Some(&Token::Eof),
+            "{:?}",
+            doc
+        );
+        assert_eq!(end, doc.len(), "{:?}", doc);
+
+        // Diagnostics slice the input using the same spans
+        let (_, errors) = yacc_parser::parse_with_recovery(&doc);
+        for error in errors {
+            error.to_diagnostic().render(&doc, "test.y", false);
+        }
+    }
+}
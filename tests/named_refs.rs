@@ -0,0 +1,122 @@
+use yacc_parser::error::GrammarError;
+use yacc_parser::grammar::Item;
+use yacc_parser::lexer::Lexer;
+use yacc_parser::token::Spanned;
+use yacc_parser::token::Token;
+
+// The reference errors of `input`, as `(error, referenced text)`
+fn reference_errors(input: &str) -> Vec<(String, &str)> {
+    yacc_parser::parse(input)
+        .unwrap()
+        .validate()
+        .into_iter()
+        .map(|error| match &error {
+            GrammarError::UnresolvedReference { reference }
+            | GrammarError::AmbiguousReference { reference } => {
+                (error.to_string(), &input[reference.span.clone()])
+            }
+            _ => panic!("unexpected error {:?}", error),
+        })
+        .collect()
+}
+
+#[test]
+fn lexes_named_references() {
+    let tokens: Vec<_> = Lexer::new("exp[left] exp [a.b-c] [] [ x] [x").collect();
+    assert_eq!(
+        tokens,
+        [
+            Spanned::new(Token::Ident, 0..3),
+            Spanned::new(Token::NamedRef, 3..9),
+            Spanned::new(Token::Ident, 10..13),
+            Spanned::new(Token::NamedRef, 14..21),
+            Spanned::new(Token::Err, 22..23),
+            Spanned::new(Token::Err, 23..24),
+            Spanned::new(Token::Err, 25..26),
+            Spanned::new(Token::Ident, 27..28),
+            Spanned::new(Token::Err, 28..29),
+            Spanned::new(Token::Err, 30..32),
+            Spanned::new(Token::Eof, 32..32),
+        ]
+    );
+}
+
+#[test]
+fn parses_named_references() {
+    let input = "%%\nexp[r]: exp[l] '+' exp;\n";
+    let grammar = yacc_parser::parse(input).unwrap();
+    let rule = grammar.rule("exp").unwrap();
+    let named_ref = rule.named_ref.as_ref().unwrap();
+    assert_eq!(named_ref.data, "r");
+    assert_eq!(&input[named_ref.span.clone()], "[r]");
+    let named_refs: Vec<_> = rule.alternatives[0]
+        .items
+        .iter()
+        .map(|item| match item {
+            Item::Symbol { named_ref, .. } => named_ref.as_ref().map(|r| r.data.as_str()),
+            Item::Action(_) => panic!("unexpected action"),
+        })
+        .collect();
+    assert_eq!(named_refs, [Some("l"), None, None]);
+}
+
+#[test]
+fn resolves_names() {
+    // Symbols are referred to by their names, or by their explicit names
+    assert!(reference_errors(
+        "%token NUM\n%%\nexp[r]: exp[l] '+' NUM { $r = $l + $NUM; @r = @l; } | NUM;\n"
+    )
+    .is_empty());
+    assert!(reference_errors("%token NUM\n%%\nexp: NUM { $exp = $[NUM]; };\n").is_empty());
+}
+
+#[test]
+fn rejects_unresolved_and_ambiguous_names() {
+    assert_eq!(
+        reference_errors("%token NUM\n%%\nexp: NUM { $$ = $num + @x; };\n"),
+        [
+            ("invalid reference `$num`".to_string(), "$num"),
+            ("invalid reference `@x`".to_string(), "@x"),
+        ]
+    );
+    assert_eq!(
+        reference_errors("%token NUM\n%%\nexp: NUM '+' NUM { $$ = $NUM; };\n"),
+        [("ambiguous reference `$NUM`".to_string(), "$NUM")]
+    );
+    // The left-hand side counts as well
+    assert_eq!(
+        reference_errors("%token NUM\n%%\nexp: exp '+' NUM { $$ = $exp; } | NUM;\n"),
+        [("ambiguous reference `$exp`".to_string(), "$exp")]
+    );
+}
+
+#[test]
+fn explicit_names_hide_symbol_names() {
+    assert_eq!(
+        reference_errors("%token NUM\n%%\nexp[r]: exp[l] '+' exp[x] { $$ = $exp; } | NUM;\n"),
+        [("invalid reference `$exp`".to_string(), "$exp")]
+    );
+    // Only the renamed symbol is hidden
+    assert!(reference_errors(
+        "%token NUM\n%%\nexp[r]: exp[l] '+' exp { $$ = $l + $exp; } | NUM;\n"
+    )
+    .is_empty());
+}
+
+#[test]
+fn mid_rule_actions_only_see_earlier_symbols() {
+    assert_eq!(
+        reference_errors("%token A B\n%%\nexp: A { $$ = $A + $B; } B { $$ = $A + $B; };\n"),
+        [("invalid reference `$B`".to_string(), "$B")]
+    );
+}
+
+#[test]
+fn prints_named_references() {
+    let input = "%%\nexp[left]: exp[l] '+' exp { $left = $l; };\n";
+    let grammar = yacc_parser::parse(input).unwrap();
+    let printed = grammar.to_string();
+    assert!(printed.contains("exp[left]:"), "{}", printed);
+    assert!(printed.contains("exp[l] '+' exp {"), "{}", printed);
+    assert_eq!(yacc_parser::parse(&printed).unwrap().to_string(), printed);
+}
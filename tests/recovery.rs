@@ -0,0 +1,85 @@
+use yacc_parser::error::ParseError;
+use yacc_parser::grammar::Directive;
+use yacc_parser::token::Token;
+
+// Two broken rules and one broken directive, each followed by valid input.
+const INPUT: &str = "\
+%token A B
+%left = A
+%type <t> b
+%%
+a: A = B;
+b: B;
+c: A : B | A;
+d: A;
+";
+
+#[test]
+fn collects_every_error() {
+    let (_, errors) = yacc_parser::parse_with_recovery(INPUT);
+    let found: Vec<_> = errors
+        .iter()
+        .map(|error| match error {
+            ParseError::UnexpectedToken { found, .. } => (found.data, error.line_col(INPUT)),
+            _ => panic!("unexpected error {:?}", error),
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (Token::Equal, (2, 7)),
+            (Token::Equal, (5, 6)),
+            (Token::Colon, (7, 6)),
+        ]
+    );
+}
+
+#[test]
+fn keeps_the_valid_parts() {
+    let (grammar, _) = yacc_parser::parse_with_recovery(INPUT);
+    assert!(matches!(
+        grammar.directives[0].data,
+        Directive::Token { .. }
+    ));
+    assert!(matches!(grammar.directives[2].data, Directive::Type { .. }));
+    let names: Vec<_> = grammar.rules.iter().map(|rule| &rule.name.data).collect();
+    assert_eq!(names, ["a", "b", "c", "d"]);
+    for name in ["b", "d"] {
+        let rule = grammar.rule(name).unwrap();
+        assert_eq!(rule.alternatives.len(), 1);
+        assert_eq!(rule.alternatives[0].symbols().count(), 1);
+    }
+}
+
+#[test]
+fn parse_returns_the_first_error() {
+    let error = yacc_parser::parse(INPUT).unwrap_err();
+    assert_eq!(error.line_col(INPUT), (2, 7));
+}
+
+#[test]
+fn a_stray_semicolon_does_not_swallow_the_next_rule() {
+    let input = "%%\na: b;;\nc: d;\n";
+    let (grammar, errors) = yacc_parser::parse_with_recovery(input);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].line_col(input), (2, 6));
+    let names: Vec<_> = grammar.rules.iter().map(|rule| &rule.name.data).collect();
+    assert_eq!(names, ["a", "c"]);
+}
+
+#[test]
+fn reports_unknown_directives_in_alternatives() {
+    let input = "%%\na: 'x' %foo;\nb: 'y' %prec 'x' %prec 'y';\nc: 'z';\n";
+    let (grammar, errors) = yacc_parser::parse_with_recovery(input);
+    assert_eq!(errors.len(), 2);
+    match &errors[0] {
+        ParseError::UnknownDirective { name } => assert_eq!(name.data, "%foo"),
+        error => panic!("unexpected error {:?}", error),
+    }
+    assert_eq!(
+        errors[1].to_string(),
+        "unexpected directive, expected identifier, character literal, string literal, code block, '|' or ';'"
+    );
+    let names: Vec<_> = grammar.rules.iter().map(|rule| &rule.name.data).collect();
+    assert_eq!(names, ["a", "b", "c"]);
+}
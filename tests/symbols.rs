@@ -0,0 +1,42 @@
+use yacc_parser::grammar::Associativity;
+use yacc_parser::grammar::Tag;
+use yacc_parser::symbols::SymbolTable;
+
+const INPUT: &str = "\
+%token <op> PLUS \"+\"
+%token <ival> NUM
+%left \"+\" '\\x41'
+%right 'B'
+%type <sval> num
+%%
+num: NUM | num PLUS 'A' | num \"+\" '\\x42';
+";
+
+#[test]
+fn resolves_aliases_and_char_literals() {
+    let grammar = yacc_parser::parse(INPUT).unwrap();
+    let table = SymbolTable::new(&grammar);
+    for (name, precedence) in [
+        ("PLUS", Some((1, Associativity::Left))),
+        ("\"+\"", Some((1, Associativity::Left))),
+        ("'A'", Some((1, Associativity::Left))),
+        ("'\\101'", Some((1, Associativity::Left))),
+        ("'\\x42'", Some((2, Associativity::Right))),
+        ("num", None),
+    ] {
+        let id = table.id(name).unwrap();
+        assert_eq!(grammar.precedence_of(name), precedence, "{}", name);
+        assert_eq!(table.get(id).precedence, precedence, "{}", name);
+    }
+    for (name, tag) in [("\"+\"", "op"), ("NUM", "ival"), ("num", "sval")] {
+        let id = table.id(name).unwrap();
+        let tag = Tag::Named(tag.to_string());
+        assert_eq!(
+            grammar.type_of(name).map(|t| &t.data),
+            Some(&tag),
+            "{}",
+            name
+        );
+        assert_eq!(table.get(id).tag.as_ref(), Some(&tag), "{}", name);
+    }
+}
@@ -0,0 +1,89 @@
+// The errors of `input`, as `(message, text at the span)`
+fn errors(input: &str) -> Vec<(String, &str)> {
+    yacc_parser::parse(input)
+        .unwrap()
+        .validate()
+        .iter()
+        .map(|error| (error.to_string(), &input[error.span()]))
+        .collect()
+}
+
+// The lints of `input`, as `(message, text at the span)`
+fn lints(input: &str) -> Vec<(String, &str)> {
+    yacc_parser::lint::lint(&yacc_parser::parse(input).unwrap())
+        .iter()
+        .map(|lint| (lint.to_string(), &input[lint.span()]))
+        .collect()
+}
+
+#[test]
+fn reports_every_use_of_an_undefined_symbol() {
+    let input = "%token A\n%%\na: A b | b 'x' %prec c;\n";
+    let errors = errors(input);
+    assert_eq!(
+        errors.iter().map(|e| e.1).collect::<Vec<_>>(),
+        ["b", "b", "c"]
+    );
+    assert_eq!(errors[0].0, "symbol `b` is not defined");
+    let spans: Vec<_> = yacc_parser::parse(input)
+        .unwrap()
+        .validate()
+        .iter()
+        .map(|error| error.span())
+        .collect();
+    assert_eq!(spans, [17..18, 21..22, 33..34]);
+}
+
+#[test]
+fn checks_destructor_and_printer_symbols() {
+    let input = "\
+%token <ival> NUM \"number\"
+%type <sval> a
+%destructor { free($$); } NUM \"number\" a 'x' '\\x41' error <ival> <sval> <*> <>
+%printer { print($$); } b \"+\" <fval>
+%%
+a: NUM 'A';
+";
+    assert_eq!(
+        errors(input),
+        [
+            ("symbol `b` is not defined".to_string(), "b"),
+            ("symbol `\"+\"` is not defined".to_string(), "\"+\""),
+            ("no symbol has the type `<fval>`".to_string(), "<fval>"),
+        ]
+    );
+}
+
+#[test]
+fn warns_about_unused_tokens() {
+    let input = "\
+%token PLUS \"+\" MINUS UMINUS UNUSED
+%%
+e: e \"+\" e | e MINUS e | '-' e %prec UMINUS | '1';
+";
+    // Tokens are also used through their alias and after `%prec`
+    assert_eq!(
+        lints(input),
+        [("token `UNUSED` is unused".to_string(), "UNUSED")]
+    );
+}
+
+#[test]
+fn warns_about_unreachable_rules() {
+    let input = "\
+%token A
+%start b
+%%
+a: c;
+b: A | d;
+c: A;
+d: %empty;
+";
+    assert_eq!(
+        lints(input),
+        [
+            ("nonterminal `a` is useless in grammar".to_string(), "a"),
+            ("nonterminal `c` is useless in grammar".to_string(), "c"),
+        ]
+    );
+}